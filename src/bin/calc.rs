@@ -0,0 +1,335 @@
+//! Calculator for the terminal.
+//! It evaluates expressions with the same engine as the desktop calculator,
+//! either interactively, from the command line or from a file with one expression per line.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::process::ExitCode;
+
+use rust_caculator::{AngleMode, CalcError, Evaluator, FractionDisplay, NumberMode, Value};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const PROMPT: &str = "> ";
+
+/// significant digits of exact fractions displayed as decimals
+const DECIMAL_DIGITS: u64 = 16;
+
+const USAGE: &str = "\
+Usage:
+  calc                 start an interactive session
+  calc -e EXPRESSION   print the result of the expression, can be repeated
+  calc --batch FILE    evaluate one expression per line of the file
+  calc < FILE          evaluate one expression per line of the standard input
+  calc -h, --help      show this help
+
+Empty lines and lines starting with # are skipped in batch mode, commands such as :vars can be used.
+The exit code is 1 if any expression fails and 2 if the arguments or the file are invalid.";
+
+const HELP: &str = "\
+Type an expression such as 2*(3+sin 1) then press Enter to evaluate it.
+The result of the last evaluation can be used as ans.
+Functions such as max, min, sum, avg, hypot and round take a list of arguments, for example max(3, 7, 2).
+x% is x/100, a percentage added or subtracted is taken of the number before it: 200 + 10% is 220.
+Modulo is written as mod, for example 7 mod 4.
+Logarithms are ln, log10, log2 and log(x, base), exponentials are exp and exp10, the constants are π and e.
+Assign a value to a variable with NAME = EXPRESSION, for example x = 3.
+Define a function with NAME(PARAMS) = EXPRESSION, for example f(x, y) = x^2 + y.
+
+Commands:
+  :help      show this help
+  :vars      list all variables
+  :funcs     list all user functions
+  :del NAME  delete a variable or a user function
+  :angle     show the angle mode, :angle deg, :angle rad or :angle grad changes it
+  :precision show the precision, :precision N computes with N significant decimal digits,
+             :precision exact computes with exact fractions such as 1/3,
+             :precision complex computes with complex numbers such as 1 + 2i
+             and :precision float computes with binary floating point numbers
+  :display   show how results are displayed, :display fraction or :display decimal changes how exact fractions
+             are displayed and :display rectangular or :display polar changes how complex numbers are displayed
+  :history   list evaluated expressions
+  :clear     forget all variables and history
+  :quit      exit, Ctrl-D also works";
+
+struct Session {
+    evaluator: Evaluator,
+    history: Vec<String>,
+    display: FractionDisplay,
+    /// complex numbers are displayed in polar form such as 2∠90
+    polar: bool,
+}
+
+impl Session {
+    fn new() -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+            history: Vec::new(),
+            display: FractionDisplay::Fraction,
+            polar: false,
+        }
+    }
+
+    /// evaluate one expression and keep its result as ans, or define a function
+    fn evaluate(&mut self, line: &str) -> Result<Option<Value>, CalcError> {
+        self.history.push(line.to_string());
+        let value = self.evaluator.execute_str(line)?;
+        if let Some(v) = &value {
+            self.evaluator.set_variable("ans", v.clone());
+        }
+        Ok(value)
+    }
+
+    /// print the result of a line, nothing is printed for a definition
+    fn format(&self, value: &Value) -> String {
+        if self.polar {
+            value.format_polar(self.evaluator.angle_mode())
+        } else if let NumberMode::Integer(_) = self.evaluator.number_mode() {
+            value.format_in(self.evaluator.number_mode())
+        } else {
+            value.format(self.display)
+        }
+    }
+
+    fn print_result(&self, value: Option<Value>) {
+        if let Some(v) = value {
+            println!("{}", self.format(&v));
+        }
+    }
+
+    /// run a command, return false if the session should be ended
+    fn command(&mut self, command: &str) -> bool {
+        match command {
+            ":help" => println!("{}", HELP),
+            ":vars" => {
+                for (name, value) in self.evaluator.variables() {
+                    println!("{} = {}", name, self.format(&value));
+                }
+            }
+            ":funcs" => {
+                for function in self.evaluator.functions() {
+                    println!("{}", function.definition(self.evaluator.library()));
+                }
+            }
+            ":history" => {
+                for (i, line) in self.history.iter().enumerate() {
+                    println!("{:4}  {}", i + 1, line);
+                }
+            }
+            ":clear" => {
+                self.evaluator.clear_variables();
+                self.evaluator.clear_functions();
+                self.history.clear();
+            }
+            ":quit" | ":exit" => return false,
+            ":angle" => println!("{}", self.evaluator.angle_mode()),
+            _ if command.starts_with(":angle ") => {
+                match command[":angle ".len()..].trim().to_lowercase().as_str() {
+                    "deg" => self.evaluator.set_angle_mode(AngleMode::Deg),
+                    "rad" => self.evaluator.set_angle_mode(AngleMode::Rad),
+                    "grad" => self.evaluator.set_angle_mode(AngleMode::Grad),
+                    mode => println!("Unknown angle mode {}, it must be deg, rad or grad", mode),
+                }
+            }
+            ":precision" => match self.evaluator.number_mode() {
+                NumberMode::Float => println!("float"),
+                NumberMode::Decimal(digits) => println!("{} digits", digits),
+                NumberMode::Rational => println!("exact"),
+                NumberMode::Complex => println!("complex"),
+                NumberMode::Integer(mode) => println!("{}", mode),
+            },
+            _ if command.starts_with(":precision ") => {
+                let precision = command[":precision ".len()..].trim().to_lowercase();
+                match precision.parse::<u64>() {
+                    Ok(digits) if digits > 0 => self.evaluator.set_number_mode(NumberMode::Decimal(digits)),
+                    _ if precision == "float" => self.evaluator.set_number_mode(NumberMode::Float),
+                    _ if precision == "exact" => self.evaluator.set_number_mode(NumberMode::Rational),
+                    _ if precision == "complex" => self.evaluator.set_number_mode(NumberMode::Complex),
+                    _ => println!("Invalid precision {}, it must be a positive number of digits, exact, complex or float", precision),
+                }
+            }
+            ":display" => {
+                let fraction = match self.display {
+                    FractionDisplay::Fraction => "fraction",
+                    FractionDisplay::Decimal(_) => "decimal",
+                };
+                println!("{}, {}", fraction, if self.polar { "polar" } else { "rectangular" });
+            }
+            _ if command.starts_with(":display ") => {
+                match command[":display ".len()..].trim().to_lowercase().as_str() {
+                    "fraction" => self.display = FractionDisplay::Fraction,
+                    "decimal" => self.display = FractionDisplay::Decimal(DECIMAL_DIGITS),
+                    "rectangular" => self.polar = false,
+                    "polar" => self.polar = true,
+                    display => println!("Unknown display {}, it must be fraction, decimal, rectangular or polar", display),
+                }
+            }
+            _ if command.starts_with(":del ") => {
+                let name = command[":del ".len()..].trim();
+                let variable = self.evaluator.remove_variable(name);
+                let function = self.evaluator.remove_function(name);
+                if variable.is_none() && function.is_none() {
+                    println!("{} is not defined", name);
+                }
+            }
+            _ => println!("Unknown command {}, type :help to see all commands", command),
+        }
+        true
+    }
+}
+
+/// point to the part of the input which causes the error, the input is expected to follow the prompt
+/// and the span to be counted from its first character which is not a space
+fn print_error(error: &CalcError, input: &str) {
+    if let Some(span) = error.span() {
+        let width = (span.end - span.start).max(1);
+        let indent = input.chars().take_while(|c| c.is_whitespace()).count();
+        println!("{}{}", " ".repeat(PROMPT.len() + indent + span.start), "^".repeat(width));
+    }
+    println!("error: {}", error);
+}
+
+fn run_interactive() -> rustyline::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let mut session = Session::new();
+
+    loop {
+        let input = match editor.readline(PROMPT) {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        };
+        let line = input.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        if line.starts_with(':') {
+            if !session.command(line) {
+                break;
+            }
+            continue;
+        }
+        match session.evaluate(line) {
+            Ok(value) => session.print_result(value),
+            Err(e) => print_error(&e, &input),
+        }
+    }
+    Ok(())
+}
+
+/// evaluate expressions given by -e, stop at the first failure
+fn run_expressions(expressions: &[String]) -> ExitCode {
+    let mut session = Session::new();
+    for expression in expressions {
+        match session.evaluate(expression) {
+            Ok(value) => session.print_result(value),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(1);
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// evaluate one expression per line, failures are reported with their line numbers
+fn run_batch(reader: impl BufRead) -> ExitCode {
+    let mut session = Session::new();
+    let mut failed = false;
+    for (i, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(2);
+            }
+        };
+        let expression = line.trim();
+        if expression.is_empty() || expression.starts_with('#') {
+            continue;
+        }
+        if expression.starts_with(':') {
+            if !session.command(expression) {
+                break;
+            }
+            continue;
+        }
+        match session.evaluate(expression) {
+            Ok(value) => session.print_result(value),
+            Err(e) => {
+                failed = true;
+                match e.span() {
+                    Some(span) => {
+                        // report the column in the original line, counted from 1
+                        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                        eprintln!("line {}, column {}: error: {}", i + 1, indent + span.start + 1, e);
+                    }
+                    None => eprintln!("line {}: error: {}", i + 1, e),
+                }
+            }
+        }
+    }
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("calc: {}", message);
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let mut expressions = Vec::new();
+    let mut batch_file = None;
+    let mut iterator = args.iter();
+    while let Some(arg) = iterator.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "-e" | "--eval" => match iterator.next() {
+                Some(expression) => expressions.push(expression.clone()),
+                None => return usage_error("missing expression after -e"),
+            },
+            "--batch" => match iterator.next() {
+                Some(file) => batch_file = Some(file.clone()),
+                None => return usage_error("missing file after --batch"),
+            },
+            _ => return usage_error(&format!("unknown argument {}", arg)),
+        }
+    }
+
+    if !expressions.is_empty() {
+        if batch_file.is_some() {
+            return usage_error("-e and --batch cannot be used together");
+        }
+        return run_expressions(&expressions);
+    }
+    if let Some(file) = batch_file {
+        return match File::open(&file) {
+            Ok(f) => run_batch(BufReader::new(f)),
+            Err(e) => {
+                eprintln!("calc: cannot open {}: {}", file, e);
+                ExitCode::from(2)
+            }
+        };
+    }
+    if !io::stdin().is_terminal() {
+        return run_batch(io::stdin().lock());
+    }
+
+    match run_interactive() {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("calc: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
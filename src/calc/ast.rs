@@ -0,0 +1,296 @@
+use std::fmt;
+use super::functions::*;
+use super::context::*;
+use super::error::*;
+use super::value::*;
+use super::is_decimal;
+
+/// An abstract syntax tree of an expression.
+/// The `name` of a node is the name of its functor, constant or variable and the `span` is the position of the name
+/// in the input, the operands of a functor are `arg`, `left` and `right` or `args`.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum Expr {
+    /// a literal number such as 3.14, it is kept as written so that it is converted to the number mode
+    /// of the context when the expression is evaluated
+    Number {
+        text: String,
+        span: Span,
+    },
+    /// a named constant such as π, its value is resolved when the expression is evaluated
+    Constant {
+        name: String,
+        span: Span,
+    },
+    /// a variable such as ans, its value is looked up in the context when the expression is evaluated
+    Variable {
+        name: String,
+        span: Span,
+    },
+    /// a functor applied to one operand, either prefix like sin 1 or postfix like 3²
+    Unary {
+        name: String,
+        arg: Box<Expr>,
+        span: Span,
+    },
+    /// an infix functor applied to two operands such as 1 + 2
+    Binary {
+        name: String,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    /// a functor called with a bracketed argument list such as sin(1)
+    Call {
+        name: String,
+        args: Vec<Expr>,
+        span: Span,
+    },
+    /// an expression surrounded by brackets
+    Group(Box<Expr>),
+    /// store the value of an expression to a variable such as x = 3, the span is the position of the variable name
+    Assign {
+        name: String,
+        value: Box<Expr>,
+        span: Span,
+    },
+}
+
+/// A line of input, either an expression to be evaluated or a definition of a user function
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum Statement {
+    /// an expression to be evaluated
+    Expression(Expr),
+    /// a user function such as f(x, y) = x^2 + y, the span is the position of the function name
+    Function {
+        name: String,
+        params: Vec<String>,
+        body: Expr,
+        span: Span,
+    },
+}
+
+/// execute the functor registered under the given name with already computed arguments,
+/// the span is the position of the functor name in the input and is used for error reporting
+pub fn apply_functor(context: &mut Context, name: &String, span: Span, args: &[Value]) -> Result<Value, CalcError> {
+    let functor = context.get_functor(name)
+        .ok_or(CalcError::UnknownFunction { token: name.clone(), span })?;
+    check_arg_count(functor.as_ref(), args.len()).map_err(|e| e.at(name, span))?;
+
+    for arg in args {
+        context.execution_stack.push_val(arg.clone());
+    }
+    functor.execute_args(context, args.len()).map_err(|e| e.at(name, span))
+}
+
+/// whether the right operand of the binary functor is a percentage of its left operand
+fn is_percent_of(context: &Context, name: &String, right: &String) -> bool {
+    context.get_functor(right).is_some_and(|f| f.id() == ID_PERCENT) &&
+        context.get_functor(name).is_some_and(|f| is_additive(f.as_ref()))
+}
+
+impl Expr {
+    /// number of levels of the expression, a single operand has one
+    pub fn height(&self) -> usize {
+        match self {
+            Expr::Number { .. } | Expr::Constant { .. } | Expr::Variable { .. } => 1,
+            Expr::Unary { arg, .. } => arg.height() + 1,
+            Expr::Binary { left, right, .. } => left.height().max(right.height()) + 1,
+            Expr::Call { args, .. } => args.iter().map(Expr::height).max().unwrap_or(0) + 1,
+            Expr::Group(inner) => inner.height() + 1,
+            Expr::Assign { value, .. } => value.height() + 1,
+        }
+    }
+
+    /// evaluate the expression, variables are looked up in the given context and assignments are stored to it
+    pub fn evaluate_in(&self, context: &mut Context) -> Result<Value, CalcError> {
+        // operators and calls are evaluated by their own functions to keep the stack frame small,
+        // it is nested once for each level of the expression and each recursive call of a user function
+        match self {
+            Expr::Number { text, span } => Value::parse(text, context.number_mode)
+                .ok_or_else(|| Self::invalid_number(context.number_mode, text, *span)),
+            Expr::Constant { name, span } => context.library.get_constant(name, context.number_mode)
+                .ok_or_else(|| CalcError::UnknownConstant { token: name.clone(), span: *span }),
+            Expr::Variable { name, span } => context.variables.get(name)
+                .ok_or_else(|| CalcError::UnknownVariable { token: name.clone(), span: *span })?
+                .to_mode(context.number_mode),
+            Expr::Unary { name, arg, span } => Self::evaluate_unary(context, name, arg, *span),
+            Expr::Binary { name, left, right, span } => Self::evaluate_binary(context, name, left, right, *span),
+            Expr::Call { name, args, span } => Self::evaluate_call(context, name, args, *span),
+            Expr::Group(inner) => inner.evaluate_in(context),
+            Expr::Assign { name, value, .. } => {
+                let value = value.evaluate_in(context)?;
+                context.variables.insert(name.clone(), value.clone());
+                Ok(value)
+            }
+        }
+    }
+
+    /// the parser accepts only valid numbers, so a number of the mode which cannot be parsed is too large for it
+    /// such as 1e400 for f64, other numbers are not written in the mode such as 2i outside complex mode
+    /// or 2.5 in programmer mode where integers are wrapped to the word instead of being too large
+    fn invalid_number(mode: NumberMode, text: &str, span: Span) -> CalcError {
+        if let NumberMode::Integer(_) = mode {
+            return CalcError::InvalidToken { token: text.to_string(), span };
+        }
+        let imaginary = mode == NumberMode::Complex && text.strip_suffix('i').is_some_and(is_decimal);
+        if is_decimal(text) || imaginary {
+            CalcError::Overflow { token: text.to_string(), span }
+        } else {
+            CalcError::InvalidToken { token: text.to_string(), span }
+        }
+    }
+
+    fn evaluate_unary(context: &mut Context, name: &String, arg: &Expr, span: Span) -> Result<Value, CalcError> {
+        let arg = arg.evaluate_in(context)?;
+        apply_functor(context, name, span, &[arg])
+    }
+
+    fn evaluate_call(context: &mut Context, name: &String, args: &[Expr], span: Span) -> Result<Value, CalcError> {
+        let mut values = Vec::new();
+        for arg in args {
+            values.push(arg.evaluate_in(context)?);
+        }
+        apply_functor(context, name, span, &values)
+    }
+
+    fn evaluate_binary(context: &mut Context, name: &String, left: &Expr, right: &Expr, span: Span) -> Result<Value, CalcError> {
+        let a = left.evaluate_in(context)?;
+        let b = match right {
+            // a percentage added to or subtracted from a number is a percentage of that number, such as 200 + 10%
+            Expr::Unary { name: percent, arg, span: percent_span } if is_percent_of(context, name, percent) => {
+                Self::evaluate_percent_of(context, percent, arg, *percent_span, &a)?
+            }
+            _ => right.evaluate_in(context)?,
+        };
+        apply_functor(context, name, span, &[a, b])
+    }
+
+    fn evaluate_percent_of(context: &mut Context, percent: &String, arg: &Expr, span: Span, base: &Value) -> Result<Value, CalcError> {
+        let p = arg.evaluate_in(context)?;
+        let p = apply_functor(context, percent, span, &[p])?;
+        percent_of(context.number_mode, p, base.clone()).map_err(|e| e.at(percent, span))
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Expr {
+    /// the expression written so that it can be parsed back, the library which it is parsed with
+    /// tells whether a functor is written before or after its operand
+    pub fn display<'a>(&'a self, library: &'a FunctionLib) -> impl fmt::Display + 'a {
+        ExprDisplay { expr: self, library }
+    }
+}
+
+struct ExprDisplay<'a> {
+    expr: &'a Expr,
+    library: &'a FunctionLib,
+}
+
+impl fmt::Display for ExprDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let library = self.library;
+        match self.expr {
+            Expr::Number { text, .. } => write!(f, "{}", text),
+            Expr::Constant { name, .. } => write!(f, "{}", name),
+            Expr::Variable { name, .. } => write!(f, "{}", name),
+            Expr::Unary { name, arg, .. } => {
+                let functor = library.get_functor(name);
+                let postfix = functor.as_ref().is_some_and(|functor| is_postfix(functor.as_ref()));
+                let arg = arg.display(library);
+                if functor.is_some_and(|functor| functor.id() == ID_NEG) {
+                    // written as unary minus so that it can be parsed back
+                    write!(f, "-{}", arg)
+                }
+                else if postfix {
+                    write!(f, "{}{}", arg, name)
+                }
+                else if is_identifier(name) {
+                    // keep a space so that the name does not stick to the operand
+                    write!(f, "{} {}", name, arg)
+                }
+                else {
+                    write!(f, "{}{}", name, arg)
+                }
+            }
+            Expr::Binary { name, left, right, .. } => write!(f, "{} {} {}", left.display(library), name, right.display(library)),
+            Expr::Call { name, args, .. } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg.display(library))?;
+                }
+                write!(f, ")")
+            }
+            Expr::Group(inner) => write!(f, "({})", inner.display(library)),
+            Expr::Assign { name, value, .. } => write!(f, "{} = {}", name, value.display(library)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::parser::parse;
+
+    #[test]
+    fn display_parses_back_to_the_same_expression() {
+        let mut context = Context::new();
+        context.variables.insert("x".to_string(), Value::Number(2.0));
+        for (expression, displayed) in [
+            ("1+2*3", "1 + 2 * 3"),
+            ("-(1+2)!", "-(1 + 2)!"),
+            ("sin 30+3²", "sin 30 + 3²"),
+            ("√16*⅟4", "√16 * ⅟4"),
+            ("max(1,x,3)-ln e", "max(1, x, 3) - ln e"),
+            ("2^-x^2", "2 ^ -x ^ 2"),
+            ("200+10%", "200 + 10%"),
+            ("y=2*π", "y = 2 * π"),
+        ] {
+            let expr = parse(expression, &context).unwrap();
+            let text = expr.display(&context.library).to_string();
+            assert_eq!(text, displayed, "{}", expression);
+            let reparsed = parse(&text, &context).unwrap();
+            assert_eq!(reparsed.display(&context.library).to_string(), text);
+            assert_eq!(reparsed.evaluate_in(&mut context), expr.evaluate_in(&mut context), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn height_counts_the_levels_of_the_tree() {
+        let context = Context::new();
+        let height = |expression| parse(expression, &context).unwrap().height();
+        assert_eq!(height("1"), 1);
+        assert_eq!(height("1 + 2 * 3"), 3);
+        assert_eq!(height("(1)"), 2);
+        assert_eq!(height("max(1, -2, 3)"), 3);
+        assert_eq!(height("+1"), 1);
+    }
+
+    #[test]
+    fn numbers_too_large_for_f64_overflow() {
+        let mut context = Context::new();
+        let evaluate = |expression, context: &mut Context| parse(expression, context).unwrap().evaluate_in(context);
+        assert_eq!(evaluate("1e-3 + 2E+2", &mut context), Ok(Value::Number(200.001)));
+        assert_eq!(evaluate("1 + 1e400", &mut context), Err(CalcError::Overflow { token: "1e400".to_string(), span: Span::new(4, 9) }));
+        // the imaginary unit is not a suffix of numbers in other modes
+        assert_eq!(evaluate("2i", &mut context), Err(CalcError::InvalidToken { token: "2i".to_string(), span: Span::new(0, 2) }));
+        context.number_mode = NumberMode::Complex;
+        assert_eq!(evaluate("1e400i", &mut context), Err(CalcError::Overflow { token: "1e400i".to_string(), span: Span::new(0, 6) }));
+        assert_eq!(evaluate("-1e400", &mut context), Err(CalcError::Overflow { token: "1e400".to_string(), span: Span::new(1, 6) }));
+    }
+
+    #[test]
+    fn rational_numbers_too_large_for_f64_overflow() {
+        let mut context = Context::new();
+        context.number_mode = NumberMode::Rational;
+        assert_eq!(parse("2 * 1e100001", &context).unwrap().evaluate_in(&mut context),
+            Err(CalcError::Overflow { token: "1e100001".to_string(), span: Span::new(4, 12) }));
+    }
+}
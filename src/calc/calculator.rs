@@ -1,8 +1,13 @@
 use std::collections::HashMap;
 use super::functions::*;
+use super::error::*;
+use super::context::AngleMode;
+use super::value::{is_complex, is_fraction, FractionDisplay, IntegerMode, NumberMode, Radix, Value};
 
 use super::{is_decimal, Evaluator};
 
+/// A keypad calculator, it receives key presses one by one and
+/// keeps the history and the immediate result to be displayed.
 pub struct Calculator {
     evaluator: Evaluator,
     constants_map: HashMap<String, String>,
@@ -12,17 +17,51 @@ pub struct Calculator {
     temp_history: String,
     input_tokens: Vec<String>,
     memory: Option<String>,
+    fraction_display: FractionDisplay,
+    polar_display: bool,
 }
+/// Keys which do not input anything to the expression
 pub enum Feature {
+    /// clear the current operand
     CE,
+    /// clear everything
     C,
+    /// store the immediate result to the memory
     MS,
+    /// recall the value stored in the memory
     MR,
+    /// delete the last input character
     DEL,
+    /// evaluate the expression
     Eval,
+    /// switch the angle mode to the next one in the order DEG, RAD, GRAD
+    DRG,
+    /// flip the sign of the operand being input, or of the last result,
+    /// the sign of the exponent is flipped once the exponent is being input
+    Negate,
+    /// start the exponent of the operand being input such as 6.022e23
+    EXP,
+    /// write integers in the given radix in programmer mode
+    Radix(Radix),
+    /// switch the word size of integers in programmer mode to the next one in the order 64, 32, 16, 8
+    WordSize,
+    /// switch integers in programmer mode between signed and unsigned
+    Signed,
+}
+
+/// a key which inputs a digit or the decimal point, the digits above 9 are input by the keys A to F of programmer mode
+fn is_digit_key(c: char) -> bool {
+    c.is_ascii_digit() || ('A'..='F').contains(&c) || c == '.'
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Calculator {
+    /// a calculator computing with f64 in radians, nothing is input yet
     pub fn new() -> Self {
         Self {
             evaluator: Evaluator::new(),
@@ -33,34 +72,52 @@ impl Calculator {
             temp_history: String::new(),
             last_immediate: String::new(),
             memory: None,
+            fraction_display: FractionDisplay::Fraction,
+            polar_display: false,
         }
     }
 
-    fn expression_operand_input(&mut self, c: &char) -> Result<Option<String>, String> {       
+    fn expression_operand_input(&mut self, c: &char) -> Result<Option<String>, CalcError> {       
         if !self.last_result.is_empty() {
             // clear last result if user input first operand of the expression
             self.last_result.clear();
         }
+        if *c == '.' && (self.operand_token.contains('.') || self.operand_token.contains('e')) {
+            // the exponent has no fraction and the mantissa has only one dot
+            return Ok(None);
+        }
         self.operand_token.push(*c);
         Ok(Some(self.operand_token.clone()))
     }
 
-    fn expression_constant_input(&mut self, const_val: &String) -> Result<Option<String>, String> {
+    fn expression_constant_input(&mut self, const_val: &str) -> Result<Option<String>, CalcError> {
         // just clear the temporary input if user pick another constant
         self.operand_token.clear();
         // clear last result we don't need it anymore
         self.last_result.clear();
 
-        self.operand_token = const_val.clone();
+        self.operand_token = const_val.to_string();
         Ok(Some(self.operand_token.clone()))
     }
 
+    /// remove an exponent which has no digit yet such as the e in 6.022e
+    fn complete_operand(&mut self) {
+        let token = &mut self.operand_token;
+        if token.ends_with('-') || token.ends_with('+') {
+            token.pop();
+        }
+        if token.ends_with('e') {
+            token.pop();
+        }
+    }
+
     fn push_temp_input(&mut self) -> Option<String> {
         let mut put_str : Option<String> = None;
         if !self.last_result.is_empty() {
             self.operand_token = self.last_result.clone();
             self.last_result.clear();
         }
+        self.complete_operand();
         if !self.operand_token.is_empty() {
             let _ = self.evaluator.put_token(&self.operand_token);
             put_str.replace(self.operand_token.clone());
@@ -70,13 +127,14 @@ impl Calculator {
         put_str
     }
 
-    fn expression_op_input(&mut self, op_name: &String) -> Result<Option<String>, String> {
-        let funtor_opt = FUNCTION_LIB.get_functor(op_name);
+    fn expression_op_input(&mut self, op_name: &String) -> Result<Option<String>, CalcError> {
+        let funtor_opt = self.evaluator.get_functor(op_name);
         let mut prefer_op_fisrt = false;
         match funtor_opt {
             Some(f) => {
-                if f.id() == ID_SQR {     
-                    let _ = self.push_temp_input();           
+                if is_postfix(f.as_ref()) {
+                    // a postfix functor applies to the operand input before it
+                    let _ = self.push_temp_input();
                 }
                 else if f.arg_count() == 1 {
                     prefer_op_fisrt = true;
@@ -105,13 +163,14 @@ impl Calculator {
             match res {
                 Err(e) => Err(e),
                 Ok(t) => {
-                    Ok(t.map(|v| v.to_string()))
+                    Ok(t.map(|v| v.format_in(self.number_mode())))
                 }
             }
         }
         
     }
 
+    /// the keys input since the last evaluation, or the expression which has just been evaluated
     pub fn build_history(&self) -> String {
         if self.temp_history.is_empty() {
             let mut history = String::new();
@@ -126,56 +185,81 @@ impl Calculator {
         }        
     }
 
-    pub fn perform_exp_input(&mut self, input: String) -> Result<Option<String>, String> {
+    /// press a key of a digit, a dot, a functor, a constant or a variable,
+    /// it returns the text to be displayed if the display changes
+    pub fn perform_exp_input(&mut self, input: String) -> Result<Option<String>, CalcError> {
         if input.is_empty() {
-            return Err("Empty input".to_string());
+            return Err(CalcError::EmptyInput);
         }
         self.temp_history.clear();
 
-        let immediate_result: Result<Option<String>, String>;
-
-        loop {
-            if input.len() == 1 {
-                let c: char = input.chars().next().unwrap();
-                if c.is_ascii_digit() || c == '.' {
-                    immediate_result = self.expression_operand_input(&c);
-                    break;
-                }
-            }
-            let constant = self.constants_map.get(&input);
-            match constant {
-                Some(value) => {
-                    immediate_result = self.expression_constant_input(&value.clone());
-                }
-                None => {
-                    immediate_result = self.expression_op_input(&input);
-                }
-            }
-            break;
+        let first_char = input.chars().next().unwrap();
+        let integer_mode = self.integer_mode();
+        let immediate_result = if let Some(mode) = integer_mode.filter(|_| input.len() == 1 && is_digit_key(first_char)) {
+            // only the digits of the radix are input in programmer mode, the others and the dot are ignored
+            if mode.radix.is_digit(first_char) { self.expression_operand_input(&first_char) } else { Ok(None) }
         }
-
-        match immediate_result.clone() {
-            Ok(Some(v)) => {
-                self.last_immediate = v;
-            },
-            _ => {}
+        else if input.len() == 1 && (first_char.is_ascii_digit() || first_char == '.') {
+            self.expression_operand_input(&first_char)
+        }
+        else if is_decimal(&input) || integer_mode.is_some_and(|mode| mode.parse(&input).is_some()) {
+            // a whole number such as a pasted 6.022e23
+            self.expression_constant_input(&input)
+        }
+        else {
+            let mode = self.number_mode();
+            let value = self.constants_map.get(&input).cloned()
+                .or_else(|| self.evaluator.get_variable(&input).map(|v| v.format_in(mode)));
+            match value {
+                Some(value) => self.expression_constant_input(&value),
+                None => self.expression_op_input(&input),
+            }
         };
+
+        if let Ok(Some(v)) = immediate_result.clone() {
+            self.last_immediate = v;
+        }
         
-        immediate_result
+        immediate_result.map(|text| text.map(|text| self.display_text(text)))
     }
 
-    pub fn perform_feature(&mut self, feature: &Feature) -> Result<Option<String>, String> {
-        match feature {
+    /// press a key which is not a part of the expression such as C or =,
+    /// it returns the text to be displayed if the display changes
+    pub fn perform_feature(&mut self, feature: &Feature) -> Result<Option<String>, CalcError> {
+        let result = match feature {
             Feature::CE => self.reset_temp(),
             Feature::C => self.reset(),
             Feature::MS => self.memory_store(),
             Feature::MR => self.memory_recover(),
             Feature::Eval => self.eval(),
             Feature::DEL => self.delete_one_char(),
+            Feature::DRG => {
+                self.set_angle_mode(self.angle_mode().next());
+                Ok(None)
+            }
+            Feature::Negate => self.negate(),
+            Feature::EXP => self.start_exponent(),
+            Feature::Radix(radix) => self.update_integer_mode(|mode| mode.radix = *radix),
+            Feature::WordSize => self.update_integer_mode(|mode| *mode = mode.next_word_size()),
+            Feature::Signed => self.update_integer_mode(|mode| mode.signed = !mode.signed),
+        };
+        result.map(|text| text.map(|text| self.display_text(text)))
+    }
+
+    /// the text displayed for an input or a result, an exact fraction or a complex number is kept as it is
+    /// so that it can be input again without losing digits, it is only displayed as a decimal or in polar form
+    fn display_text(&self, text: String) -> String {
+        match self.fraction_display {
+            FractionDisplay::Decimal(_) if is_fraction(&text) => Value::parse(&text, NumberMode::Rational)
+                .map_or(text, |v| v.format(self.fraction_display)),
+            _ if self.polar_display && is_complex(&text) => Value::parse(&text, NumberMode::Complex)
+                .map_or(text, |v| v.format_polar(self.angle_mode())),
+            _ => text,
         }
     }
 
-    fn eval(&mut self) -> Result<Option<String>, String> {
+    fn eval(&mut self) -> Result<Option<String>, CalcError> {
+        self.complete_operand();
         let mut temp_token_updated = false;
         if !self.operand_token.is_empty() {
             let _ = self.evaluator.put_token(&self.operand_token);
@@ -185,12 +269,12 @@ impl Calculator {
         }
         let res = self.evaluator.evaluate();
         match res {
-            Some(v) => {
+            Ok(v) => {
                 // store the final result so that it can be used as the begin of next expression
-                self.last_result = v.to_string();
+                self.last_result = v.format_in(self.number_mode());
                 self.last_immediate = self.last_result.clone();
                 // reset the evaluator after evaluation
-                self.evaluator = Evaluator::new();
+                self.evaluator.reset();
 
                 self.temp_history = self.build_history() + " =";
                 self.operand_token.clear();
@@ -199,9 +283,9 @@ impl Calculator {
                 // return the result in String
                 Ok(Some(self.last_result.clone()))
             },
-            None => {
+            Err(_) => {
                 // reset the evaluator due to it may damaged by evaluation
-                self.evaluator = Evaluator::new();
+                self.evaluator.reset();
 
                 // recover evaluator to state before evaluation
                 if temp_token_updated {
@@ -216,9 +300,72 @@ impl Calculator {
         }
     }
 
-    fn recaculate_after_delete(&mut self) -> Result<Option<String>, String> {
+    fn negate(&mut self) -> Result<Option<String>, CalcError> {
+        let integer_mode = self.integer_mode();
+        // the last result becomes the operand being input when an operator is pressed, so it can be negated the same way
+        let operand = if !self.operand_token.is_empty() {
+            &mut self.operand_token
+        }
+        else if !self.last_result.is_empty() {
+            &mut self.last_result
+        }
+        else {
+            // the last input is an operator or a bracket, there is no number to negate
+            return Ok(None);
+        };
+
+        if let Some(mode) = integer_mode {
+            // the two's complement of the word is written in the radix
+            if let Some(value) = mode.parse(operand) {
+                *operand = mode.format(-value);
+            }
+        }
+        else if is_complex(operand) {
+            // both parts of a complex number change their signs
+            if let Some(value) = Value::parse(operand, NumberMode::Complex) {
+                *operand = Value::Complex(-value.to_complex()).to_string();
+            }
+        }
+        else {
+            // once the exponent is being input, its sign is flipped instead of the sign of the mantissa
+            let start = operand.find('e').map_or(0, |i| i + 1);
+            if operand[start..].starts_with('-') {
+                operand.remove(start);
+            }
+            else if start > 0 || operand.chars().any(|c| c.is_ascii_digit() && c != '0') {
+                // zero has no sign
+                operand.insert(start, '-');
+            }
+        }
+        let operand = operand.clone();
+        self.temp_history.clear();
+        Ok(Some(operand))
+    }
+
+    fn start_exponent(&mut self) -> Result<Option<String>, CalcError> {
+        if self.integer_mode().is_some() {
+            // integers in programmer mode have no exponent
+            return Ok(None);
+        }
+        if self.operand_token.is_empty() && (is_fraction(&self.last_result) || is_complex(&self.last_result)) {
+            // a fraction or a complex number has no exponent
+            return Ok(None);
+        }
+        if self.operand_token.is_empty() {
+            // the exponent applies to the last result if there is one, otherwise it is a power of ten such as 1e5
+            self.operand_token = if self.last_result.is_empty() { "1".to_string() } else { self.last_result.clone() };
+            self.last_result.clear();
+        }
+        if !self.operand_token.contains('e') {
+            self.operand_token.push('e');
+        }
+        self.temp_history.clear();
+        Ok(Some(self.operand_token.clone()))
+    }
+
+    fn recaculate_after_delete(&mut self) -> Result<Option<String>, CalcError> {
         // reset the evaluator due to its state is one step forward
-        self.evaluator = Evaluator::new();
+        self.evaluator.reset();
 
         // recover evaluator to current state of inputs
         let mut results = Vec::new();
@@ -228,11 +375,11 @@ impl Calculator {
         }
 
         if self.operand_token.is_empty() {
-            let mut last_val = 0.0;
+            let mut last_val = Value::Number(0.0);
             let i_opt = results.iter().rev().position(|r| {
                 match r {
                     Ok(Some(v)) => {
-                        last_val = *v;
+                        last_val = v.clone();
                         true
                     },
                     _ => false
@@ -241,7 +388,7 @@ impl Calculator {
             
             match i_opt {
                 Some(_) => {
-                    Ok(Some(last_val.to_string()))
+                    Ok(Some(last_val.format_in(self.number_mode())))
                 },
                 None => Ok(Some("0".to_string()))
             }
@@ -252,11 +399,15 @@ impl Calculator {
         }
     }
 
-    fn delete_one_char(&mut self) -> Result<Option<String>, String> {
+    fn delete_one_char(&mut self) -> Result<Option<String>, CalcError> {
 
         // try to delete one last char in temporary input...
         match self.operand_token.pop() {
             Some(_) => {
+                if self.operand_token == "-" {
+                    // a sign without digits is not an operand
+                    self.operand_token.clear();
+                }
                 // ...if it's possible then return the new temporary input
                 if self.operand_token.is_empty() {
                     self.recaculate_after_delete()
@@ -284,44 +435,46 @@ impl Calculator {
         }
     }
 
-    pub fn reset(&mut self) -> Result<Option<String>, String> {
+    /// clear the input and the results like C, the modes, variables and functions are kept
+    pub fn reset(&mut self) -> Result<Option<String>, CalcError> {
         self.last_result = "0".to_string();
         self.last_immediate = "0".to_string();
         self.operand_token.clear();
         self.input_tokens.clear();
-        self.evaluator = Evaluator::new();
+        self.evaluator.reset();
         self.temp_history.clear();
 
         Ok(Some(self.last_result.clone()))
     }
 
-    fn reset_temp(&mut self) -> Result<Option<String>, String> {
+    fn reset_temp(&mut self) -> Result<Option<String>, CalcError> {
         self.operand_token.clear();
         self.last_result.clear();
 
-        let ctx = self.evaluator.excution_context.borrow();
-        let last_val_opt = ctx.execution_stack.top_val();
+        let last_val_opt = self.evaluator.top_value();
         match last_val_opt {
             Some(v) => {
-                self.last_immediate = v.to_string();
+                self.last_immediate = v.format_in(self.evaluator.number_mode());
                 Ok(Some(self.last_immediate.clone()))
             },
             None => Ok(Some("0".to_string()))
         }
     }
 
-    fn memory_store(&mut self) -> Result<Option<String>, String> {
+    fn memory_store(&mut self) -> Result<Option<String>, CalcError> {
         if self.last_immediate.is_empty() {
             return Ok(None);
         }
 
-        if is_decimal(self.last_immediate.as_str()) {
+        let immediate = self.last_immediate.as_str();
+        let is_integer = self.integer_mode().is_some_and(|mode| mode.parse(immediate).is_some());
+        if is_decimal(immediate) || is_fraction(immediate) || is_complex(immediate) || is_integer {
             self.memory.replace(self.last_immediate.clone());
         }
         Ok(None)
     }
 
-    fn memory_recover(&mut self) -> Result<Option<String>, String> {
+    fn memory_recover(&mut self) -> Result<Option<String>, CalcError> {
         match self.memory.clone() {
             Some(v) => {
                 self.operand_token = v.clone();
@@ -331,7 +484,210 @@ impl Calculator {
         }
     }
 
+    /// define a constant which is input as the given text when its key is pressed
     pub fn add_constant(&mut self, name: String, value: String) {
         self.constants_map.insert(name, value);
     }
-}
\ No newline at end of file
+
+    /// define a variable which can be input by its name like a constant, it is kept after C is pressed
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.evaluator.set_variable(name, value);
+    }
+
+    /// remove a variable, it returns the value it had
+    pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
+        self.evaluator.remove_variable(name)
+    }
+
+    /// all variables sorted by name
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.evaluator.variables()
+    }
+
+    /// unit of angles used by trigonometric keys
+    pub fn angle_mode(&self) -> AngleMode {
+        self.evaluator.angle_mode()
+    }
+
+    /// set the unit of angles used by trigonometric keys, it is kept after C is pressed
+    pub fn set_angle_mode(&mut self, mode: AngleMode) {
+        self.evaluator.set_angle_mode(mode);
+    }
+
+    /// how numbers are computed and displayed
+    pub fn number_mode(&self) -> NumberMode {
+        self.evaluator.number_mode()
+    }
+
+    /// set how numbers are computed and displayed, such as decimals with 32 significant digits,
+    /// it is kept after C is pressed
+    pub fn set_number_mode(&mut self, mode: NumberMode) {
+        let old_mode = self.number_mode();
+        self.evaluator.set_number_mode(mode);
+        let is_integer = |mode: NumberMode| matches!(mode, NumberMode::Integer(_));
+        if old_mode == mode || !(is_integer(old_mode) || is_integer(mode)) {
+            return;
+        }
+
+        // operands are written in the radix of programmer mode, so the input so far is written again in the new mode
+        let convert = |text: &String| Value::parse(text, old_mode)
+            .and_then(|v| v.to_mode(mode).ok())
+            .map_or_else(|| text.clone(), |v| v.format_in(mode));
+        self.operand_token = convert(&self.operand_token);
+        self.last_result = convert(&self.last_result);
+        self.last_immediate = convert(&self.last_immediate);
+        self.memory = self.memory.as_ref().map(convert);
+        self.input_tokens = self.input_tokens.iter().map(convert).collect();
+
+        // recover evaluator to current state of inputs
+        self.evaluator.reset();
+        for token in &self.input_tokens {
+            let _ = self.evaluator.put_token(token);
+        }
+    }
+
+    /// the word size, the signedness and the radix of integers if the calculator is in programmer mode
+    pub fn integer_mode(&self) -> Option<IntegerMode> {
+        match self.number_mode() {
+            NumberMode::Integer(mode) => Some(mode),
+            _ => None,
+        }
+    }
+
+    /// change the integer mode in programmer mode, the operand or the result being displayed is returned in the new mode
+    fn update_integer_mode<F>(&mut self, update: F) -> Result<Option<String>, CalcError>
+    where
+        F: FnOnce(&mut IntegerMode),
+    {
+        let Some(mut mode) = self.integer_mode() else {
+            return Ok(None);
+        };
+        update(&mut mode);
+        self.set_number_mode(NumberMode::Integer(mode));
+
+        let displayed = [&self.operand_token, &self.last_result, &self.last_immediate].into_iter()
+            .find(|text| !text.is_empty());
+        Ok(displayed.cloned())
+    }
+
+    /// how exact fractions are displayed
+    pub fn fraction_display(&self) -> FractionDisplay {
+        self.fraction_display
+    }
+
+    /// set how exact fractions are displayed, it is kept after C is pressed
+    pub fn set_fraction_display(&mut self, display: FractionDisplay) {
+        self.fraction_display = display;
+    }
+
+    /// whether complex numbers are displayed in polar form
+    pub fn polar_display(&self) -> bool {
+        self.polar_display
+    }
+
+    /// display complex numbers in polar form such as 2∠90 instead of 2i, it is kept after C is pressed
+    pub fn set_polar_display(&mut self, polar: bool) {
+        self.polar_display = polar;
+    }
+
+    /// functors and constants of this calculator, functions registered here can be input by their names
+    pub fn library_mut(&mut self) -> &mut FunctionLib {
+        self.evaluator.library_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// press the keys in order and return the text displayed after the last one
+    fn press(calculator: &mut Calculator, keys: &[&str]) -> Result<Option<String>, CalcError> {
+        let mut displayed = Ok(None);
+        for key in keys {
+            displayed = calculator.perform_exp_input(key.to_string());
+        }
+        displayed
+    }
+
+    #[test]
+    fn exp_key_starts_the_exponent_of_the_operand() {
+        let mut calculator = Calculator::new();
+        press(&mut calculator, &["6"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::EXP), Ok(Some("6e".to_string())));
+        assert_eq!(press(&mut calculator, &["2"]), Ok(Some("6e2".to_string())));
+        // the sign of the exponent is flipped rather than the sign of the mantissa
+        assert_eq!(calculator.perform_feature(&Feature::Negate), Ok(Some("6e-2".to_string())));
+        press(&mut calculator, &["*", "2"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::Eval), Ok(Some("0.12".to_string())));
+    }
+
+    #[test]
+    fn exp_key_without_an_operand() {
+        let mut calculator = Calculator::new();
+        // the exponent applies to the last result
+        press(&mut calculator, &["1", "2"]).unwrap();
+        calculator.perform_feature(&Feature::Eval).unwrap();
+        calculator.perform_feature(&Feature::EXP).unwrap();
+        press(&mut calculator, &["3"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::Eval), Ok(Some("12000".to_string())));
+
+        // it is a power of ten after an operator
+        press(&mut calculator, &["+"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::EXP), Ok(Some("1e".to_string())));
+        press(&mut calculator, &["2"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::Eval), Ok(Some("12100".to_string())));
+    }
+
+    #[test]
+    fn exp_key_is_ignored_for_fractions_and_integers() {
+        let mut calculator = Calculator::new();
+        calculator.set_number_mode(NumberMode::Rational);
+        press(&mut calculator, &["1", "/", "3"]).unwrap();
+        calculator.perform_feature(&Feature::Eval).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::EXP), Ok(None));
+
+        calculator.set_number_mode(NumberMode::Integer(IntegerMode::default()));
+        press(&mut calculator, &["5"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::EXP), Ok(None));
+    }
+
+    #[test]
+    fn radix_keys_rewrite_the_result() {
+        let mut calculator = Calculator::new();
+        calculator.set_number_mode(NumberMode::Integer(IntegerMode::default()));
+        assert_eq!(calculator.perform_feature(&Feature::Radix(Radix::Hex)), Ok(Some("0".to_string())));
+        press(&mut calculator, &["F", "F", "+", "1"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::Eval), Ok(Some("100".to_string())));
+        assert_eq!(calculator.perform_feature(&Feature::Radix(Radix::Dec)), Ok(Some("256".to_string())));
+    }
+
+    #[test]
+    fn word_size_key_wraps_the_result() {
+        let mut calculator = Calculator::new();
+        calculator.set_number_mode(NumberMode::Integer(IntegerMode::default()));
+        press(&mut calculator, &["2", "5", "6"]).unwrap();
+        calculator.perform_feature(&Feature::Eval).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::WordSize), Ok(Some("256".to_string())));
+        assert_eq!(calculator.perform_feature(&Feature::WordSize), Ok(Some("256".to_string())));
+        // the result is wrapped when the word becomes too small for it
+        assert_eq!(calculator.perform_feature(&Feature::WordSize), Ok(Some("0".to_string())));
+        assert_eq!(calculator.integer_mode().map(|mode| mode.bits()), Some(8));
+    }
+
+    #[test]
+    fn digit_keys_of_other_radixes_are_ignored() {
+        let mut calculator = Calculator::new();
+        calculator.set_number_mode(NumberMode::Integer(IntegerMode::new(Radix::Dec, 8, true).unwrap()));
+        calculator.perform_feature(&Feature::Signed).unwrap();
+        assert_eq!(press(&mut calculator, &["A"]), Ok(None));
+        press(&mut calculator, &["5"]).unwrap();
+        // the two's complement of the word is written
+        assert_eq!(calculator.perform_feature(&Feature::Negate), Ok(Some("251".to_string())));
+        assert_eq!(calculator.perform_feature(&Feature::Radix(Radix::Bin)), Ok(Some("11111011".to_string())));
+
+        // leaving programmer mode writes the operand as a decimal number again
+        calculator.set_number_mode(NumberMode::Float);
+        press(&mut calculator, &["+", "5"]).unwrap();
+        assert_eq!(calculator.perform_feature(&Feature::Eval), Ok(Some("256".to_string())));
+    }
+}
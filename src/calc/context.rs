@@ -1,70 +1,227 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use bigdecimal::num_traits::Euclid;
+use num_complex::Complex64;
+use super::functions::*;
+use super::user_function::*;
+use super::ast::Expr;
+use super::decimal;
+use super::value::*;
 
-use std::cell::RefCell;
+/// Unit of angles taken and returned by trigonometric functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    /// a full turn is 360
+    Deg,
+    /// a full turn is 2π
+    #[default]
+    Rad,
+    /// a full turn is 400
+    Grad,
+}
+
+impl AngleMode {
+    fn full_turn(&self) -> f64 {
+        match self {
+            AngleMode::Deg => 360.0,
+            AngleMode::Rad => 2.0 * PI,
+            AngleMode::Grad => 400.0,
+        }
+    }
+
+    /// convert an angle in this unit to radians
+    pub fn to_radians(&self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Rad => angle,
+            // reduce to one turn first so that large angles lose no precision
+            _ => (angle % self.full_turn()) * 2.0 * PI / self.full_turn(),
+        }
+    }
+
+    /// convert an angle in radians to this unit
+    pub fn from_radians(&self, radians: f64) -> f64 {
+        match self {
+            AngleMode::Rad => radians,
+            _ => radians * self.full_turn() / (2.0 * PI),
+        }
+    }
+
+    /// number of right angles in 0..4 after removing full turns if the angle is an exact multiple of a right angle,
+    /// trigonometric functions have exact results for such angles
+    pub fn right_angles(&self, angle: f64) -> Option<u8> {
+        if *self == AngleMode::Rad {
+            return if angle == 0.0 { Some(0) } else { None };
+        }
+        let quarters = angle / (self.full_turn() / 4.0);
+        if quarters.is_finite() && quarters.fract() == 0.0 {
+            Some(quarters.rem_euclid(4.0) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// the same as to_radians with decimals, π is computed to the given number of significant digits
+    pub fn to_radians_decimal(&self, angle: &BigDecimal, precision: u64) -> BigDecimal {
+        match self {
+            AngleMode::Rad => angle.clone(),
+            _ => {
+                let turn = BigDecimal::from(self.full_turn() as i64);
+                let pi = decimal::pi(precision);
+                // the turn is never zero
+                decimal::div(&((angle % &turn) * pi.double()), &turn, precision).unwrap()
+            }
+        }
+    }
+
+    /// the same as from_radians with decimals, π is computed to the given number of significant digits
+    pub fn from_radians_decimal(&self, radians: &BigDecimal, precision: u64) -> BigDecimal {
+        match self {
+            AngleMode::Rad => radians.clone(),
+            _ => {
+                let turn = BigDecimal::from(self.full_turn() as i64);
+                let pi = decimal::pi(precision);
+                // π is never zero
+                decimal::div(&(radians * turn), &pi.double(), precision).unwrap()
+            }
+        }
+    }
 
-thread_local! {
-    // default context for a thread    
-    pub static CURRENT_CONTEXT: RefCell<Context> = RefCell::new(Context::new());
+    /// the same as right_angles with decimals
+    pub fn right_angles_decimal(&self, angle: &BigDecimal) -> Option<u8> {
+        if *self == AngleMode::Rad {
+            return if angle.is_zero() { Some(0) } else { None };
+        }
+        let quarter = BigDecimal::from(self.full_turn() as i64 / 4);
+        let quarters = decimal::to_integer(&(angle / quarter))?;
+        quarters.rem_euclid(&4.into()).to_u8()
+    }
+
+    /// the same as to_radians with complex numbers, only the real part is reduced to one turn
+    pub fn to_radians_complex(&self, angle: Complex64) -> Complex64 {
+        Complex64::new(self.to_radians(angle.re), angle.im * self.to_radians(1.0))
+    }
+
+    /// the same as from_radians with complex angles
+    pub fn from_radians_complex(&self, radians: Complex64) -> Complex64 {
+        radians * self.from_radians(1.0)
+    }
+
+    /// the mode after this one, in the order DEG, RAD, GRAD
+    pub fn next(&self) -> AngleMode {
+        match self {
+            AngleMode::Deg => AngleMode::Rad,
+            AngleMode::Rad => AngleMode::Grad,
+            AngleMode::Grad => AngleMode::Deg,
+        }
+    }
+}
+
+impl fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AngleMode::Deg => write!(f, "DEG"),
+            AngleMode::Rad => write!(f, "RAD"),
+            AngleMode::Grad => write!(f, "GRAD"),
+        }
+    }
 }
 
+/// Operands and intermediate results of an evaluation
 pub struct Stack {
-    stack_buffer: Vec<f64>,
+    stack_buffer: Vec<Value>,
 }
+/// State of an evaluation, functors pop their arguments from its execution stack
 pub struct Context {
+    /// operands and results of the functors which are executed
     pub execution_stack: Stack,
-    pub error_detected: bool,
-    pub error_message: String,
+    /// named values which can be used in expressions, such as ans
+    pub variables: HashMap<String, Value>,
+    /// functions defined at runtime by their names
+    pub functions: HashMap<String, UserFunction>,
+    /// number of user function calls being executed, used to detect runaway recursion
+    pub call_depth: usize,
+    /// sum of the heights of the user function bodies being executed, used to limit the nesting of the evaluation
+    pub body_depth: usize,
+    /// built-in and registered functors and constants, starts as a copy of the built-in library
+    pub library: FunctionLib,
+    /// unit of angles used by trigonometric functions
+    pub angle_mode: AngleMode,
+    /// how numbers are represented, literals and constants are converted to it
+    pub number_mode: NumberMode,
+    next_function_id: FunctionId,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Context {
+    /// a context with the built-in library and no variable, computing with f64 in radians
     pub fn new() -> Self {
         Self {
             execution_stack: Stack::new(),
-            error_detected: false,
-            error_message: String::new(),
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            call_depth: 0,
+            body_depth: 0,
+            library: FUNCTION_LIB.clone(),
+            angle_mode: AngleMode::default(),
+            number_mode: NumberMode::default(),
+            next_function_id: ID_USER_FUNCTION_BASE,
         }
     }
-    pub fn scope_current<F, T>(context:& RefCell<Context>, scope: F) -> T
-    where F: FnOnce(&RefCell<Context>) -> T {
-        CURRENT_CONTEXT.with(|c| {
-            c.swap(context);
 
-            let t = scope(c);
+    /// find a user function or a functor of the library by its name
+    pub fn get_functor(&self, name: &String) -> Option<Box<dyn Functor>> {
+        match self.functions.get(name) {
+            Some(f) => Some(Box::new(f.clone())),
+            None => self.library.get_functor(name),
+        }
+    }
 
-            context.swap(c);
-            // return
-            t
-        })
+    /// define a user function, an existing one with the same name is replaced
+    pub fn define_function(&mut self, name: &str, params: Vec<String>, body: Expr) {
+        let id = self.next_function_id;
+        self.next_function_id += 1;
+        self.functions.insert(name.to_string(), UserFunction::new(id, name.to_string(), params, body));
     }
+}
 
-    pub fn with_current<F>(f: F)
-    where F: FnOnce(&RefCell<Context>)
-    {
-        CURRENT_CONTEXT.with(|c| {
-            f(c);
-        });
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Stack {
+    /// an empty stack
     pub fn new() -> Self {
         Self {
             stack_buffer: Vec::new(),
         }
     }
 
-    pub fn push_val(&mut self, val: f64) {
+    /// push a value on the top of the stack
+    pub fn push_val(&mut self, val: Value) {
         self.stack_buffer.push(val);
     }
 
-    pub fn pop_val(&mut self) -> Option<f64> {
+    /// remove the value on the top of the stack, none if it is empty
+    pub fn pop_val(&mut self) -> Option<Value> {
         self.stack_buffer.pop()
     }
 
-    pub fn top_val(&self) -> Option<&f64> {
+    /// the value on the top of the stack, none if it is empty
+    pub fn top_val(&self) -> Option<&Value> {
         self.stack_buffer.last()
     }
 
+    /// number of values on the stack
     pub fn size(&self) -> usize {
         self.stack_buffer.len()
     }
-}
\ No newline at end of file
+}
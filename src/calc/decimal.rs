@@ -0,0 +1,491 @@
+use std::num::NonZeroU64;
+use std::str::FromStr;
+use bigdecimal::{BigDecimal, Context as DecimalContext, RoundingMode, One, Signed, ToPrimitive, Zero};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::num_traits::{Euclid, Pow};
+use num_rational::BigRational;
+use super::error::CalcError;
+
+/// digits computed in addition to the requested precision so that the rounded result is correct
+pub const GUARD_DIGITS: u64 = 10;
+
+/// largest integer which factorials, permutations and combinations are computed for,
+/// the product of more numbers takes too long to be useful
+const MAX_PRODUCT_TERMS: u64 = 10000;
+
+/// largest number of digits of a product computed by factorials, permutations and combinations,
+/// it is estimated as the number of terms times the digits of the largest term
+const MAX_PRODUCT_DIGITS: u64 = 100000;
+
+/// largest power of ten of a decimal which is converted to an exact fraction
+const MAX_RATIONAL_EXPONENT: u64 = 100000;
+
+fn context(precision: u64) -> DecimalContext {
+    DecimalContext::new(NonZeroU64::new(precision.max(1)).unwrap(), RoundingMode::HalfEven)
+}
+
+/// round to the given number of significant digits, trailing zeros are removed
+pub fn round(x: &BigDecimal, precision: u64) -> BigDecimal {
+    context(precision).round_decimal_ref(x).normalized()
+}
+
+/// the shortest decimal which converts back to the same f64
+pub fn from_f64(value: f64) -> Result<BigDecimal, CalcError> {
+    if !value.is_finite() {
+        return Err(CalcError::overflow());
+    }
+    BigDecimal::from_str(&value.to_string()).map_err(|_| CalcError::overflow())
+}
+
+/// the nearest f64, it is infinite if the decimal is out of range of f64
+pub fn to_f64(x: &BigDecimal) -> f64 {
+    x.to_f64().unwrap_or(f64::NAN)
+}
+
+/// the exact fraction equal to a decimal, none if its exponent is too large to keep all of its digits
+pub fn to_rational(x: &BigDecimal) -> Option<BigRational> {
+    let (digits, scale) = x.as_bigint_and_exponent();
+    if scale.unsigned_abs() > MAX_RATIONAL_EXPONENT {
+        return None;
+    }
+    let power = BigInt::from(10).pow(scale.unsigned_abs());
+    Some(if scale >= 0 { BigRational::new(digits, power) } else { BigRational::from_integer(digits * power) })
+}
+
+/// the fraction divided to the given number of significant digits
+pub fn from_rational(x: &BigRational, precision: u64) -> BigDecimal {
+    let quotient = div(&BigDecimal::from(x.numer().clone()), &BigDecimal::from(x.denom().clone()), precision);
+    // the denominator of a fraction is never zero
+    round(&quotient.unwrap(), precision)
+}
+
+/// integer value of a decimal, none if it has a fraction
+pub fn to_integer(x: &BigDecimal) -> Option<BigInt> {
+    if !x.is_integer() {
+        return None;
+    }
+    let (digits, scale) = x.with_scale(0).into_bigint_and_exponent();
+    debug_assert_eq!(scale, 0);
+    Some(digits)
+}
+
+pub fn div(a: &BigDecimal, b: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    if b.is_zero() {
+        return Err(CalcError::division_by_zero());
+    }
+    let ctx = context(precision + GUARD_DIGITS);
+    Ok(ctx.multiply(a, &ctx.invert(b)))
+}
+
+/// exact remainder of a divided by b with the sign of a. The digits are reduced by the power of ten modulo the
+/// divisor so a number such as 1e300000000 is not expanded to all of its digits
+pub fn rem(a: &BigDecimal, b: &BigDecimal) -> Result<BigDecimal, CalcError> {
+    if b.is_zero() {
+        return Err(CalcError::division_by_zero());
+    }
+    if a.abs() < b.abs() {
+        return Ok(a.clone());
+    }
+    // a = digits_a * 10^-scale_a is reduced at the larger scale of both numbers where the divisor is an integer,
+    // the divisor has no more digits than a because it is not larger than a
+    let (digits_a, scale_a) = a.as_bigint_and_exponent();
+    let (digits_b, scale_b) = b.abs().as_bigint_and_exponent();
+    let scale = scale_a.max(scale_b);
+    let modulus = digits_b * BigInt::from(10).pow((scale - scale_b) as u64);
+    let power = BigInt::from(10).modpow(&BigInt::from(scale - scale_a), &modulus);
+    let remainder = digits_a.abs() % &modulus * power % &modulus;
+    Ok(BigDecimal::new(if a.is_negative() { -remainder } else { remainder }, scale))
+}
+
+pub fn sqrt(x: &BigDecimal, precision: u64) -> BigDecimal {
+    x.sqrt_with_context(&context(precision + GUARD_DIGITS)).unwrap_or_else(BigDecimal::zero)
+}
+
+/// π computed by Machin's formula π/4 = 4 atan(1/5) - atan(1/239)
+pub fn pi(precision: u64) -> BigDecimal {
+    let work = precision + GUARD_DIGITS;
+    let ctx = context(work);
+    let a = atan_series(&ctx.invert(&BigDecimal::from(5)), work);
+    let b = atan_series(&ctx.invert(&BigDecimal::from(239)), work);
+    round(&((a * BigDecimal::from(4) - b) * BigDecimal::from(4)), precision)
+}
+
+/// sum a series whose terms are computed from the previous term and its index,
+/// it stops when a term is too small to change the sum at the given precision
+fn sum_series<F>(first: BigDecimal, work: u64, mut next: F) -> BigDecimal
+where
+    F: FnMut(&BigDecimal, u64) -> BigDecimal,
+{
+    let ctx = context(work);
+    let epsilon = BigDecimal::new(BigInt::one(), work as i64 + 2);
+    let mut term = first;
+    let mut sum = term.clone();
+    let mut k = 1;
+    while term.abs() > epsilon {
+        term = ctx.round_decimal(next(&term, k));
+        sum += &term;
+        k += 1;
+    }
+    ctx.round_decimal(sum)
+}
+
+/// Taylor series of arctangent, it converges quickly only for small arguments
+fn atan_series(x: &BigDecimal, work: u64) -> BigDecimal {
+    let ctx = context(work);
+    let x2 = ctx.round_decimal(x.square());
+    // each term is x^(2k+1)/(2k+1) with alternating signs, it is computed from the power of the previous term
+    let mut power = x.clone();
+    sum_series(x.clone(), work, |_, k| {
+        power = ctx.multiply(&power, &x2);
+        let term = ctx.multiply(&power, &ctx.invert(&BigDecimal::from(2 * k + 1)));
+        if k % 2 == 1 { -term } else { term }
+    })
+}
+
+pub fn atan(x: &BigDecimal, precision: u64) -> BigDecimal {
+    let work = precision + GUARD_DIGITS;
+    if x.is_zero() {
+        return BigDecimal::zero();
+    }
+    if x.abs() > BigDecimal::one() {
+        // atan(x) = ±π/2 - atan(1/x)
+        let half_pi = pi(work).half();
+        let inverse = atan(&context(work).invert(x), work);
+        let result = if x.is_positive() { half_pi - inverse } else { -half_pi - inverse };
+        return round(&result, precision);
+    }
+    // halve the angle twice by atan(x) = 2 atan(x / (1 + √(1 + x²))) so that the series converges quickly
+    let mut y = x.clone();
+    for _ in 0..2 {
+        let root = sqrt(&(BigDecimal::one() + y.square()), work);
+        y = context(work).multiply(&y, &context(work).invert(&(BigDecimal::one() + root)));
+    }
+    round(&(atan_series(&y, work) * BigDecimal::from(4)), precision)
+}
+
+pub fn asin(x: &BigDecimal, precision: u64) -> BigDecimal {
+    let work = precision + GUARD_DIGITS;
+    if x.abs() == BigDecimal::one() {
+        let half_pi = pi(precision).half();
+        return if x.is_positive() { half_pi } else { -half_pi };
+    }
+    let cos = sqrt(&(BigDecimal::one() - x.square()), work);
+    atan(&context(work).multiply(x, &context(work).invert(&cos)), precision)
+}
+
+pub fn acos(x: &BigDecimal, precision: u64) -> BigDecimal {
+    let work = precision + GUARD_DIGITS;
+    round(&(pi(work).half() - asin(x, work)), precision)
+}
+
+/// sine and cosine of an angle in radians
+pub fn sin_cos(x: &BigDecimal, precision: u64) -> Result<(BigDecimal, BigDecimal), CalcError> {
+    // digits of the integer part are lost when the angle is reduced, so π needs as many more digits
+    let magnitude = x.order_of_magnitude().max(0) as u64;
+    if magnitude > 1000 {
+        return Err(CalcError::domain_error("Angle is too large to compute trigonometric functions"));
+    }
+    let work = precision + GUARD_DIGITS + magnitude;
+    let ctx = context(work);
+
+    // reduce the angle to r in -π/4..π/4 where x = r + n π/2
+    let half_pi = pi(work).half();
+    let n = ctx.multiply(x, &ctx.invert(&half_pi)).with_scale_round(0, RoundingMode::HalfEven);
+    let r = ctx.round_decimal(x - &n * &half_pi);
+    let quadrant = to_integer(&n).and_then(|n| n.rem_euclid(&BigInt::from(4)).to_u8()).unwrap_or(0);
+
+    let r2 = ctx.round_decimal(r.square());
+    let sin = sum_series(r.clone(), work, |term, k| {
+        -ctx.multiply(&ctx.multiply(term, &r2), &ctx.invert(&BigDecimal::from((2 * k) * (2 * k + 1))))
+    });
+    let cos = sum_series(BigDecimal::one(), work, |term, k| {
+        -ctx.multiply(&ctx.multiply(term, &r2), &ctx.invert(&BigDecimal::from((2 * k - 1) * (2 * k))))
+    });
+    let (sin, cos) = match quadrant {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    };
+    Ok((round(&sin, precision), round(&cos, precision)))
+}
+
+pub fn exp(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    // e^x has more than a billion digits before or after the point beyond these limits
+    let limit = BigDecimal::from(1_000_000_000);
+    if *x > limit {
+        return Err(CalcError::overflow());
+    }
+    if *x < -limit {
+        return Ok(BigDecimal::zero());
+    }
+    Ok(round(&x.exp_with_context(&context(precision + GUARD_DIGITS)), precision))
+}
+
+/// natural logarithm of a number from 1 to 10
+fn ln_reduced(m: &BigDecimal, work: u64) -> BigDecimal {
+    // take the square root three times so that the number is close to 1, then ln(y) = 2 atanh((y - 1) / (y + 1))
+    let mut y = m.clone();
+    for _ in 0..3 {
+        y = sqrt(&y, work);
+    }
+    let ctx = context(work);
+    let z = ctx.multiply(&(&y - BigDecimal::one()), &ctx.invert(&(&y + BigDecimal::one())));
+    let z2 = ctx.round_decimal(z.square());
+    let mut power = z.clone();
+    let sum = sum_series(z, work, |_, k| {
+        power = ctx.multiply(&power, &z2);
+        ctx.multiply(&power, &ctx.invert(&BigDecimal::from(2 * k + 1)))
+    });
+    sum * BigDecimal::from(16)
+}
+
+/// natural logarithm of a positive number
+pub fn ln(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    if !x.is_positive() {
+        return Err(CalcError::domain_error("Logarithm of non-positive number is undefined"));
+    }
+    let work = precision + GUARD_DIGITS;
+    // x = m * 10^e where m is from 1 to 10, so ln(x) = ln(m) + e ln(10)
+    let e = x.order_of_magnitude();
+    let (digits, scale) = x.as_bigint_and_exponent();
+    let m = BigDecimal::new(digits, scale + e);
+    let mut result = ln_reduced(&m, work);
+    if e != 0 {
+        result += ln_reduced(&BigDecimal::from(10), work) * BigDecimal::from(e);
+    }
+    Ok(round(&result, precision))
+}
+
+/// logarithm of a positive number to a positive base other than 1
+pub fn log(x: &BigDecimal, base: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    let work = precision + GUARD_DIGITS;
+    let numerator = ln(x, work)?;
+    let denominator = ln(base, work)?;
+    div(&numerator, &denominator, precision).map(|v| round(&v, precision))
+}
+
+/// a number raised to a power, integer powers are computed by multiplications
+pub fn pow(a: &BigDecimal, b: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    if a.is_zero() {
+        return if b.is_negative() { Err(CalcError::division_by_zero()) }
+            else if b.is_zero() { Ok(BigDecimal::one()) }
+            else { Ok(BigDecimal::zero()) };
+    }
+    if let Some(n) = to_integer(b).and_then(|n| n.to_i64()) {
+        if n.unsigned_abs() <= 1_000_000 {
+            return Ok(round(&a.powi_with_context(n, &context(precision + GUARD_DIGITS)), precision));
+        }
+    }
+    if a.is_negative() {
+        return Err(CalcError::domain_error("Power of negative number is undefined"));
+    }
+    let work = precision + GUARD_DIGITS;
+    exp(&(ln(a, work)? * b), precision)
+}
+
+/// e^|x| / 2 for a number so large that e^-|x| is below every digit of the precision, none for other numbers,
+/// sinh and cosh use it instead of the exponentials whose difference would align billions of digits
+fn half_exp_of_large(x: &BigDecimal, precision: u64) -> Result<Option<BigDecimal>, CalcError> {
+    let a = x.abs();
+    let limit = BigDecimal::from(precision * 2 + 10);
+    if a <= limit {
+        return Ok(None);
+    }
+    exp(&a, precision + GUARD_DIGITS).map(|v| Some(round(&v.half(), precision)))
+}
+
+pub fn sinh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    let work = precision + GUARD_DIGITS;
+    if x.abs() < BigDecimal::one() {
+        // the difference of the exponentials loses digits for small numbers, the Taylor series does not
+        let ctx = context(work);
+        let x2 = ctx.round_decimal(x.square());
+        let sum = sum_series(x.clone(), work, |term, k| {
+            ctx.multiply(&ctx.multiply(term, &x2), &ctx.invert(&BigDecimal::from((2 * k) * (2 * k + 1))))
+        });
+        return Ok(round(&sum, precision));
+    }
+    if let Some(half) = half_exp_of_large(x, precision)? {
+        return Ok(if x.is_negative() { -half } else { half });
+    }
+    let a = exp(x, work)?;
+    let b = exp(&-x, work)?;
+    Ok(round(&(a - b).half(), precision))
+}
+
+pub fn cosh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    if let Some(half) = half_exp_of_large(x, precision)? {
+        return Ok(half);
+    }
+    let work = precision + GUARD_DIGITS;
+    let a = exp(x, work)?;
+    let b = exp(&-x, work)?;
+    Ok(round(&(a + b).half(), precision))
+}
+
+pub fn tanh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    // tanh is 1 to every digit of the precision for large numbers and the exponentials would be too large
+    let limit = BigDecimal::from(precision * 2 + 10);
+    if x.abs() > limit {
+        return Ok(if x.is_positive() { BigDecimal::one() } else { -BigDecimal::one() });
+    }
+    let work = precision + GUARD_DIGITS;
+    div(&sinh(x, work)?, &cosh(x, work)?, precision).map(|v| round(&v, precision))
+}
+
+pub fn asinh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    // asinh(-x) = -asinh(x), the logarithm of a number less than 1 loses digits
+    let work = precision + GUARD_DIGITS;
+    let a = x.abs();
+    let result = ln(&(sqrt(&(a.square() + BigDecimal::one()), work) + &a), precision)?;
+    Ok(if x.is_negative() { -result } else { result })
+}
+
+pub fn acosh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    let work = precision + GUARD_DIGITS;
+    ln(&(sqrt(&(x.square() - BigDecimal::one()), work) + x), precision)
+}
+
+pub fn atanh(x: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+    let work = precision + GUARD_DIGITS;
+    let ratio = div(&(BigDecimal::one() + x), &(BigDecimal::one() - x), work)?;
+    Ok(round(&ln(&ratio, work)?.half(), precision))
+}
+
+/// product of the integers from `from` to `to`, both included
+fn product(from: &BigInt, to: &BigInt) -> BigInt {
+    let mut result = BigInt::one();
+    let mut i = from.clone();
+    while i <= *to {
+        result *= &i;
+        i += 1;
+    }
+    result
+}
+
+/// check that a product of the given number of terms up to the largest one is small enough to be computed
+fn check_product_terms(count: &BigInt, largest: &BigInt) -> Result<(), CalcError> {
+    // log10(2) is a little more than 3/10
+    let digits = largest.bits() * 3 / 10 + 1;
+    if *count > BigInt::from(MAX_PRODUCT_TERMS) || count * digits > BigInt::from(MAX_PRODUCT_DIGITS) {
+        Err(CalcError::overflow())
+    } else {
+        Ok(())
+    }
+}
+
+/// integer value of a decimal which is a term of a product, none if it is negative or has a fraction.
+/// An integer with too many digits to be a term fails with overflow before it is expanded to all of its digits
+/// such as 1e100000000
+pub fn to_natural(x: &BigDecimal) -> Result<Option<BigInt>, CalcError> {
+    if x.is_negative() || !x.is_integer() {
+        return Ok(None);
+    }
+    if x.order_of_magnitude() >= MAX_PRODUCT_DIGITS as i64 {
+        return Err(CalcError::overflow());
+    }
+    Ok(to_integer(x))
+}
+
+pub fn factorial(n: &BigInt) -> Result<BigDecimal, CalcError> {
+    check_product_terms(n, n)?;
+    Ok(BigDecimal::from(product(&BigInt::one(), n)))
+}
+
+/// number of permutations of r items chosen from n items
+pub fn permutation(n: &BigInt, r: &BigInt) -> Result<BigDecimal, CalcError> {
+    check_product_terms(r, n)?;
+    Ok(BigDecimal::from(product(&(n - r + 1), n)))
+}
+
+/// number of combinations of r items chosen from n items
+pub fn combination(n: &BigInt, r: &BigInt) -> Result<BigDecimal, CalcError> {
+    // nCr is the same as nC(n - r), choose the smaller one to have less terms
+    let r = r.min(&(n - r)).clone();
+    check_product_terms(&r, n)?;
+    Ok(BigDecimal::from(product(&(n - &r + 1), n) / product(&BigInt::one(), &r)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> BigDecimal {
+        BigDecimal::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn round_to_significant_digits() {
+        assert_eq!(round(&decimal("1.234567"), 5), decimal("1.2346"));
+        assert_eq!(round(&decimal("2.5"), 1), decimal("2"));
+        assert_eq!(round(&decimal("123456"), 2), decimal("1.2e5"));
+        assert_eq!(from_rational(&BigRational::new(1.into(), 3.into()), 5), decimal("0.33333"));
+    }
+
+    #[test]
+    fn constants_to_the_precision() {
+        assert_eq!(pi(40), decimal("3.141592653589793238462643383279502884197"));
+        assert_eq!(exp(&BigDecimal::one(), 40), Ok(decimal("2.718281828459045235360287471352662497757")));
+        assert_eq!(round(&sqrt(&BigDecimal::from(2), 40), 40), decimal("1.414213562373095048801688724209698078570"));
+        assert_eq!(ln(&BigDecimal::from(10), 40), Ok(decimal("2.302585092994045684017991454684364207601")));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(div(&BigDecimal::one(), &BigDecimal::from(3), 5).map(|v| round(&v, 5)), Ok(decimal("0.33333")));
+        assert_eq!(div(&BigDecimal::one(), &BigDecimal::zero(), 5), Err(CalcError::division_by_zero()));
+        assert_eq!(pow(&BigDecimal::zero(), &BigDecimal::from(-1), 5), Err(CalcError::division_by_zero()));
+    }
+
+    #[test]
+    fn logarithms_and_powers_out_of_their_domain() {
+        assert_eq!(ln(&BigDecimal::zero(), 10), Err(CalcError::domain_error("Logarithm of non-positive number is undefined")));
+        assert_eq!(log(&BigDecimal::from(1000), &BigDecimal::from(10), 10), Ok(BigDecimal::from(3)));
+        assert_eq!(pow(&BigDecimal::from(-8), &decimal("0.5"), 10), Err(CalcError::domain_error("Power of negative number is undefined")));
+        assert_eq!(exp(&BigDecimal::from(2_000_000_000), 10), Err(CalcError::overflow()));
+        assert_eq!(exp(&BigDecimal::from(-2_000_000_000), 10), Ok(BigDecimal::zero()));
+    }
+
+    #[test]
+    fn products_are_exact() {
+        assert_eq!(factorial(&25.into()), Ok(decimal("15511210043330985984000000")));
+        assert_eq!(permutation(&5.into(), &2.into()), Ok(BigDecimal::from(20)));
+        assert_eq!(combination(&52.into(), &5.into()), Ok(BigDecimal::from(2598960)));
+        assert_eq!(factorial(&20000.into()), Err(CalcError::overflow()));
+        assert_eq!(permutation(&BigInt::from(10).pow(50u32), &5000.into()), Err(CalcError::overflow()));
+    }
+
+    #[test]
+    fn remainders_keep_the_sign_of_the_dividend() {
+        assert_eq!(rem(&decimal("7"), &decimal("3")), Ok(decimal("1")));
+        assert_eq!(rem(&decimal("-7"), &decimal("3")), Ok(decimal("-1")));
+        assert_eq!(rem(&decimal("7"), &decimal("-3")), Ok(decimal("1")));
+        assert_eq!(rem(&decimal("7.5"), &decimal("0.2")), Ok(decimal("0.1")));
+        assert_eq!(rem(&decimal("1.25e3"), &decimal("1e2")), Ok(decimal("50")));
+        assert_eq!(rem(&decimal("2"), &decimal("1e300000000")), Ok(decimal("2")));
+        assert_eq!(rem(&decimal("7"), &decimal("0")), Err(CalcError::division_by_zero()));
+        // 10^6 = 1 modulo 7
+        assert_eq!(rem(&decimal("1e300000000"), &decimal("7")), Ok(decimal("1")));
+        assert_eq!(rem(&decimal("-3e300000002"), &decimal("0.7")), Ok(decimal("-0.4")));
+    }
+
+    #[test]
+    fn huge_terms_are_not_expanded() {
+        assert_eq!(to_natural(&decimal("120")), Ok(Some(120.into())));
+        assert_eq!(to_natural(&decimal("-3")), Ok(None));
+        assert_eq!(to_natural(&decimal("2.5")), Ok(None));
+        assert_eq!(to_natural(&decimal("1e100000000")), Err(CalcError::overflow()));
+    }
+
+    #[test]
+    fn hyperbolic_functions_of_large_numbers() {
+        // e^-x is below every digit of the precision so it is not computed
+        let half = exp(&BigDecimal::from(100_000_000), 32 + GUARD_DIGITS).map(|v| round(&v.half(), 32));
+        assert_eq!(sinh(&BigDecimal::from(100_000_000), 32), half);
+        assert_eq!(sinh(&BigDecimal::from(-100_000_000), 32), half.clone().map(|v| -v));
+        assert_eq!(cosh(&BigDecimal::from(-100_000_000), 32), half);
+        assert_eq!(cosh(&decimal("2e9"), 32), Err(CalcError::overflow()));
+        assert_eq!(sinh(&BigDecimal::from(80), 5), Ok(decimal("2.7703e34")));
+    }
+}
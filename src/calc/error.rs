@@ -0,0 +1,276 @@
+use std::fmt;
+use super::value::ValueKind;
+
+/// A range of character positions in the input, the end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// position of the first character
+    pub start: usize,
+    /// position after the last character
+    pub end: usize,
+}
+
+impl Span {
+    /// the span from the start position to the end position
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+        }
+    }
+}
+
+/// All errors can be raised while parsing or evaluating an expression.
+/// Errors raised by a functor do not know where the functor is in the input,
+/// the evaluator fills in the offending token and its span by calling `at`.
+/// The `token` of a variant is the offending token and the `span` is its position in the input,
+/// `expected` and `found` are what the functor requires and what it is given.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum CalcError {
+    /// the input is empty or blank
+    EmptyInput,
+    /// a number which is not valid in the number mode
+    InvalidToken { token: String, span: Span },
+    /// a symbol which is not a functor
+    UnknownFunction { token: String, span: Span },
+    /// a constant which is not defined in the number mode such as i outside complex mode
+    UnknownConstant { token: String, span: Span },
+    /// a name which is neither a functor, a constant nor an assigned variable
+    UnknownVariable { token: String, span: Span },
+    /// the name of a functor or a constant is assigned or defined as a function
+    InvalidAssignment { token: String, span: Span },
+    /// a token where it cannot be such as an operand following another one
+    UnexpectedToken { token: String, span: Span },
+    /// the input ends where an operand or a close bracket is expected
+    UnexpectedEnd { span: Span },
+    /// an open bracket without a close bracket or the other way around
+    UnbalancedBracket { token: String, span: Span },
+    /// a functor is missing an operand
+    StackUnderflow { token: String, span: Span },
+    /// a function is called with too few or too many arguments
+    ArgumentCount { token: String, span: Span, expected: usize, found: usize },
+    /// user functions call each other more than 256 calls deep, the recursion may never end
+    RecursionLimit { token: String, span: Span },
+    /// an expression or the bodies of the user functions it calls are nested too deeply to be evaluated
+    TooDeep { token: String, span: Span },
+    /// a divisor or a modulus is zero
+    DivisionByZero { token: String, span: Span },
+    /// an argument out of the domain of a functor such as the square root of a negative number
+    DomainError { token: String, span: Span, message: String },
+    /// a result too large for the number mode
+    Overflow { token: String, span: Span },
+    /// a value of a kind that the functor does not accept such as a string added to a number
+    TypeError { token: String, span: Span, expected: ValueKind, found: ValueKind },
+    /// an expression which cannot be evaluated, it is not bound to the input
+    InvalidExpression,
+}
+
+impl CalcError {
+    /// a functor is missing an operand
+    pub fn stack_underflow() -> Self {
+        CalcError::StackUnderflow { token: String::new(), span: Span::default() }
+    }
+
+    /// a functor divides by zero
+    pub fn division_by_zero() -> Self {
+        CalcError::DivisionByZero { token: String::new(), span: Span::default() }
+    }
+
+    /// an argument out of the domain of a functor, the message explains the domain
+    pub fn domain_error(message: &str) -> Self {
+        CalcError::DomainError { token: String::new(), span: Span::default(), message: message.to_string() }
+    }
+
+    /// a result of a functor is too large for the number mode
+    pub fn overflow() -> Self {
+        CalcError::Overflow { token: String::new(), span: Span::default() }
+    }
+
+    /// an argument of a kind that the functor does not accept
+    pub fn type_error(expected: ValueKind, found: ValueKind) -> Self {
+        CalcError::TypeError { token: String::new(), span: Span::default(), expected, found }
+    }
+
+    /// the offending token, if the error is bound to a token
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            CalcError::InvalidToken { token, .. } |
+            CalcError::UnknownFunction { token, .. } |
+            CalcError::UnknownConstant { token, .. } |
+            CalcError::UnknownVariable { token, .. } |
+            CalcError::InvalidAssignment { token, .. } |
+            CalcError::UnexpectedToken { token, .. } |
+            CalcError::UnbalancedBracket { token, .. } |
+            CalcError::StackUnderflow { token, .. } |
+            CalcError::ArgumentCount { token, .. } |
+            CalcError::RecursionLimit { token, .. } |
+            CalcError::TooDeep { token, .. } |
+            CalcError::DivisionByZero { token, .. } |
+            CalcError::DomainError { token, .. } |
+            CalcError::Overflow { token, .. } |
+            CalcError::TypeError { token, .. } => Some(token.as_str()),
+            _ => None,
+        }
+    }
+
+    /// character span of the offending part of the input, if it is known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CalcError::InvalidToken { span, .. } |
+            CalcError::UnknownFunction { span, .. } |
+            CalcError::UnknownConstant { span, .. } |
+            CalcError::UnknownVariable { span, .. } |
+            CalcError::InvalidAssignment { span, .. } |
+            CalcError::UnexpectedToken { span, .. } |
+            CalcError::UnexpectedEnd { span } |
+            CalcError::UnbalancedBracket { span, .. } |
+            CalcError::StackUnderflow { span, .. } |
+            CalcError::ArgumentCount { span, .. } |
+            CalcError::RecursionLimit { span, .. } |
+            CalcError::TooDeep { span, .. } |
+            CalcError::DivisionByZero { span, .. } |
+            CalcError::DomainError { span, .. } |
+            CalcError::Overflow { span, .. } |
+            CalcError::TypeError { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// forget where the error is in the input but keep the offending token,
+    /// used when the error is raised by an expression which is not part of the current input
+    pub fn detached(mut self) -> Self {
+        match &mut self {
+            CalcError::InvalidToken { span, .. } |
+            CalcError::UnknownFunction { span, .. } |
+            CalcError::UnknownConstant { span, .. } |
+            CalcError::UnknownVariable { span, .. } |
+            CalcError::InvalidAssignment { span, .. } |
+            CalcError::UnexpectedToken { span, .. } |
+            CalcError::UnexpectedEnd { span } |
+            CalcError::UnbalancedBracket { span, .. } |
+            CalcError::StackUnderflow { span, .. } |
+            CalcError::ArgumentCount { span, .. } |
+            CalcError::RecursionLimit { span, .. } |
+            CalcError::TooDeep { span, .. } |
+            CalcError::DivisionByZero { span, .. } |
+            CalcError::DomainError { span, .. } |
+            CalcError::Overflow { span, .. } |
+            CalcError::TypeError { span, .. } => *span = Span::default(),
+            _ => {}
+        }
+        self
+    }
+
+    /// bind the error to the given token unless it is already bound to one,
+    /// a detached error keeps its token and takes only the given location
+    pub fn at(mut self, name: &str, location: Span) -> Self {
+        match &mut self {
+            CalcError::InvalidToken { token, span } |
+            CalcError::UnknownFunction { token, span } |
+            CalcError::UnknownConstant { token, span } |
+            CalcError::UnknownVariable { token, span } |
+            CalcError::InvalidAssignment { token, span } |
+            CalcError::UnexpectedToken { token, span } |
+            CalcError::UnbalancedBracket { token, span } |
+            CalcError::StackUnderflow { token, span } |
+            CalcError::ArgumentCount { token, span, .. } |
+            CalcError::RecursionLimit { token, span } |
+            CalcError::TooDeep { token, span } |
+            CalcError::DivisionByZero { token, span } |
+            CalcError::DomainError { token, span, .. } |
+            CalcError::Overflow { token, span } |
+            CalcError::TypeError { token, span, .. } => {
+                if token.is_empty() {
+                    *token = name.to_string();
+                    *span = location;
+                }
+                else if *span == Span::default() {
+                    *span = location;
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::EmptyInput => write!(f, "Empty input"),
+            CalcError::InvalidToken { token, .. } => write!(f, "Invalid token '{}'", token),
+            CalcError::UnknownFunction { token, .. } => write!(f, "No functor found for '{}'", token),
+            CalcError::UnknownConstant { token, .. } => write!(f, "No constant found for '{}'", token),
+            CalcError::UnknownVariable { token, .. } => write!(f, "Variable '{}' is not defined", token),
+            CalcError::InvalidAssignment { token, .. } => write!(f, "Cannot assign to '{}'", token),
+            CalcError::UnexpectedToken { token, .. } => write!(f, "Unexpected token '{}'", token),
+            CalcError::UnexpectedEnd { .. } => write!(f, "Unexpected end of expression"),
+            CalcError::UnbalancedBracket { token, .. } => write!(f, "Unbalanced bracket '{}'", token),
+            CalcError::StackUnderflow { token, .. } => write!(f, "Missing operand for '{}'", token),
+            CalcError::ArgumentCount { token, expected, found, .. } => {
+                write!(f, "{} requires {} argument(s) but {} given", token, expected, found)
+            }
+            CalcError::RecursionLimit { token, .. } => write!(f, "Too many nested calls of '{}', the recursion may never end", token),
+            CalcError::TooDeep { token, .. } => write!(f, "Expression is nested too deeply at '{}'", token),
+            CalcError::DivisionByZero { .. } => write!(f, "Divide to zero"),
+            CalcError::DomainError { message, .. } => write!(f, "{}", message),
+            CalcError::Overflow { token, .. } => write!(f, "Result of '{}' is too large", token),
+            CalcError::TypeError { token, expected, found, .. } => {
+                write!(f, "{} requires a value of type {} but {} given", token, expected, found)
+            }
+            CalcError::InvalidExpression => write!(f, "Invalid expression"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_an_error_to_its_token_once() {
+        let error = CalcError::division_by_zero();
+        assert_eq!((error.token(), error.span()), (Some(""), Some(Span::default())));
+
+        let error = error.at("/", Span::new(2, 3));
+        assert_eq!((error.token(), error.span()), (Some("/"), Some(Span::new(2, 3))));
+
+        // an error is reported at the innermost functor which raises it
+        let error = error.at("f", Span::new(0, 1));
+        assert_eq!(error, CalcError::DivisionByZero { token: "/".to_string(), span: Span::new(2, 3) });
+    }
+
+    #[test]
+    fn detached_error_keeps_its_token() {
+        let error = CalcError::UnknownVariable { token: "x".to_string(), span: Span::new(4, 5) }.detached();
+        assert_eq!((error.token(), error.span()), (Some("x"), Some(Span::default())));
+
+        let error = error.at("f", Span::new(0, 1));
+        assert_eq!(error, CalcError::UnknownVariable { token: "x".to_string(), span: Span::new(0, 1) });
+    }
+
+    #[test]
+    fn errors_without_a_token() {
+        assert_eq!((CalcError::EmptyInput.token(), CalcError::EmptyInput.span()), (None, None));
+        assert_eq!(CalcError::InvalidExpression.at("+", Span::new(0, 1)), CalcError::InvalidExpression);
+
+        let error = CalcError::UnexpectedEnd { span: Span::new(3, 3) };
+        assert_eq!((error.token(), error.span()), (None, Some(Span::new(3, 3))));
+        assert_eq!(error.clone().at("+", Span::new(0, 1)), error);
+    }
+
+    #[test]
+    fn display_the_offending_token() {
+        assert_eq!(CalcError::UnknownVariable { token: "x".to_string(), span: Span::new(0, 1) }.to_string(),
+            "Variable 'x' is not defined");
+        assert_eq!(CalcError::ArgumentCount { token: "hypot".to_string(), span: Span::new(0, 5), expected: 2, found: 1 }.to_string(),
+            "hypot requires 2 argument(s) but 1 given");
+        assert_eq!(CalcError::type_error(ValueKind::Number, ValueKind::String).at("+", Span::new(1, 2)).to_string(),
+            "+ requires a value of type number but string given");
+        assert_eq!(CalcError::domain_error("Square root of a negative number").to_string(), "Square root of a negative number");
+        assert_eq!(CalcError::UnexpectedEnd { span: Span::new(3, 3) }.to_string(), "Unexpected end of expression");
+    }
+}
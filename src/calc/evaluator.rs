@@ -1,7 +1,19 @@
 use super::functions::*;
 use super::context::*;
-use std::cell::RefCell;
+use super::ast::*;
+use super::error::*;
+use super::tokenizer::*;
+use super::parser;
+use super::user_function::UserFunction;
+use super::value::*;
 
+/// exponent of a number in scientific notation such as the 23 in 6.022e23, it may have a sign
+fn is_exponent(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// a decimal number such as -3.14, it may be written in scientific notation such as 6.022e23
 pub fn is_decimal(s : &str) -> bool {
     if s.is_empty() {
         return false;
@@ -28,157 +40,709 @@ pub fn is_decimal(s : &str) -> bool {
                 return false;
             }
         }
+        else if (c == 'e' || c == 'E') && i > 0 {
+            return is_exponent(iterator.as_str());
+        }
         else if !c.is_ascii_digit() {
             return false;
         }
         
         oc = iterator.next();
-        i = i + 1;
+        i += 1;
     }
 
     true
 }
 
+/// whether brackets following the functor enclose its argument list,
+/// it is true for functions and for functors which accept a variable number of arguments such as log
+fn takes_argument_list(functor: &dyn Functor) -> bool {
+    functor.id() != ID_OPEN_BRACKET &&
+        (functor.notation() == Notation::Function || functor.max_arg_count() != Some(functor.arg_count() as usize))
+}
+
+/// Evaluate an expression, either from tokens put one by one or from a whole string
 pub struct Evaluator {
-    pub excution_context: RefCell<Context>,
-    op_stack: Vec<Box<dyn Functor>>,
+    context: Context,
+    op_stack: Vec<(Box<dyn Functor>, Token)>,
+    // size of the execution stack when each open bracket was put, used to count the arguments of a function
+    bracket_stack: Vec<usize>,
+    // character position of the next token put to the evaluator
+    input_position: usize,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Evaluator {
+    /// an evaluator with the built-in functors and constants, computing with f64 in radians
     pub fn new() -> Self {
         Self {
-            excution_context: RefCell::new(Context::new()),
-            op_stack: Vec::new()
+            context: Context::new(),
+            op_stack: Vec::new(),
+            bracket_stack: Vec::new(),
+            input_position: 0,
         }
     }
 
-    fn push_op(&mut self, op: Box<dyn Functor>) {
-        self.op_stack.push(op);
+    fn push_op(&mut self, op: Box<dyn Functor>, token: Token) {
+        self.op_stack.push((op, token));
     }
 
-    fn pop_op(&mut self) {
-        self.op_stack.pop();
+    fn pop_op(&mut self) -> Option<(Box<dyn Functor>, Token)> {
+        self.op_stack.pop()
     }
 
-    fn top_op(&self) -> Option<& Box<dyn Functor>> {
-        self.op_stack.last()
+    fn top_op(&self) -> Option<&dyn Functor> {
+        self.op_stack.last().map(|(op, _)| op.as_ref())
     }
 
+    /// number of functors put which are waiting for their operands
     pub fn op_size(&self) -> usize {
         self.op_stack.len()
     }
 
-    pub fn evaluate(&mut self) -> Option<f64> {        
-        Context::scope_current(&self.excution_context, |c| {
-            while self.op_stack.len() > 0 && c.borrow().error_detected == false {
-                let top: Box<dyn Functor> = self.op_stack.pop().unwrap();
-                top.execute();
-            }
-            if c.borrow().execution_stack.size() != 1 || c.borrow().error_detected {
-                return None;
+    fn execute_op(&mut self, op: &(Box<dyn Functor>, Token)) -> Result<(), CalcError> {
+        let (functor, token) = op;
+        let value = functor.execute(&mut self.context).map_err(|e| e.at(&token.text, token.span))?;
+        self.context.execution_stack.push_val(value);
+        Ok(())
+    }
+
+    /// execute the functors waiting for their operands and return the result of the tokens put so far
+    pub fn evaluate(&mut self) -> Result<Value, CalcError> {
+        while let Some(top) = self.pop_op() {
+            if top.0.id() == ID_OPEN_BRACKET {
+                return Err(CalcError::UnbalancedBracket { token: top.1.text, span: top.1.span });
             }
-            c.borrow().execution_stack.top_val().map(|v| v.clone())
-        })
+            self.execute_op(&top)?;
+        }
+        let ctx = &self.context;
+        if ctx.execution_stack.size() != 1 {
+            return Err(CalcError::InvalidExpression);
+        }
+        Ok(ctx.execution_stack.top_val().unwrap().clone())
     }
 
-    pub fn put_token(&mut self, token: &String) -> Result<Option<f64>, String>{
+    /// put the next token of an expression, it returns the intermediate result if a part of the expression is computed
+    pub fn put_token(&mut self, token: &String) -> Result<Option<Value>, CalcError>{
         if token.is_empty() {
-            return Err("Empty token".to_string());
+            return Err(CalcError::EmptyInput);
+        }
+        let start = self.input_position;
+        self.input_position += token.chars().count();
+        let span = Span::new(start, self.input_position);
+
+        let mode = self.context.number_mode;
+        let is_integer = matches!(mode, NumberMode::Integer(integer_mode) if integer_mode.parse(token).is_some());
+        if is_decimal(token) || is_fraction(token) || is_complex(token) || is_integer {
+            return self.put_operand(token, span);
+        }
+        if let Some(value) = self.context.library.get_constant(token, mode) {
+            self.context.execution_stack.push_val(value.clone());
+            return Ok(Some(value));
         }
-        if is_decimal(token) {
-            return self.put_operand(token);
+        if let Some(value) = self.context.variables.get(token) {
+            let value = value.to_mode(mode).map_err(|e| e.at(token, span))?;
+            self.context.execution_stack.push_val(value.clone());
+            return Ok(Some(value));
         }
         
         if token.chars().next().unwrap().is_ascii_digit() {
             // functor is not allow leading by a digit
-            return Err("Invalid token".to_string());
+            return Err(CalcError::InvalidToken { token: token.clone(), span });
         }
         
-        self.put_functor(token)
+        self.put_functor(Token::new(TokenKind::Symbol, token.clone(), span))
     }
 
-    fn eval_for_close_bracket(&mut self) -> Result<Option<f64>, String> {
-        let result = Context::scope_current(&self.excution_context, |c| {
-            while self.op_stack.len() > 0 {
-                let top = self.op_stack.pop().unwrap();
-                if top.id() == ID_OPEN_BRACKET {
-                    return Ok(c.borrow().execution_stack.top_val().map(|f| f.clone()));
-                }
-                top.execute();
-                if c.borrow().error_detected {
-                    if c.borrow().error_message.is_empty() {
-                        return Err("Error".to_string());
-                    }
-                    return Err(c.borrow().error_message.clone());
+    /// parse a whole expression such as "2*(3+sin 1)" into a syntax tree which can be evaluated later
+    pub fn parse_str(&self, expression: &str) -> Result<Expr, CalcError> {
+        parser::parse(expression, &self.context)
+    }
+
+    /// evaluate a whole expression such as "2*(3+sin 1)" from scratch, only variables and user functions are kept
+    pub fn evaluate_str(&mut self, expression: &str) -> Result<Value, CalcError> {
+        let expr = self.parse_str(expression)?;
+        self.evaluate_expr(&expr)
+    }
+
+    /// evaluate an expression parsed before, it may be parsed by another evaluator with the same functors
+    pub fn evaluate_expr(&mut self, expr: &Expr) -> Result<Value, CalcError> {
+        self.reset();
+        expr.evaluate_in(&mut self.context)
+    }
+
+    /// evaluate an expression or define a user function such as "f(x) = x^2",
+    /// it returns none for a definition
+    pub fn execute_str(&mut self, line: &str) -> Result<Option<Value>, CalcError> {
+        self.reset();
+        match parser::parse_statement(line, &self.context)? {
+            Statement::Expression(expr) => expr.evaluate_in(&mut self.context).map(Some),
+            Statement::Function { name, params, body, .. } => {
+                self.context.define_function(&name, params, body);
+                Ok(None)
+            }
+        }
+    }
+
+    /// find a user function or a built-in functor by its name
+    pub fn get_functor(&self, name: &String) -> Option<Box<dyn Functor>> {
+        self.context.get_functor(name)
+    }
+
+    /// all user functions sorted by name
+    pub fn functions(&self) -> Vec<&UserFunction> {
+        let mut functions: Vec<&UserFunction> = self.context.functions.values().collect();
+        functions.sort_by(|a, b| a.name().cmp(b.name()));
+        functions
+    }
+
+    /// remove a user function, it returns the removed function
+    pub fn remove_function(&mut self, name: &str) -> Option<UserFunction> {
+        self.context.functions.remove(name)
+    }
+
+    /// remove all user functions
+    pub fn clear_functions(&mut self) {
+        self.context.functions.clear();
+    }
+
+    /// unit of angles used by trigonometric functions
+    pub fn angle_mode(&self) -> AngleMode {
+        self.context.angle_mode
+    }
+
+    /// set the unit of angles used by trigonometric functions, it is kept after reset
+    pub fn set_angle_mode(&mut self, mode: AngleMode) {
+        self.context.angle_mode = mode;
+    }
+
+    /// how numbers are represented
+    pub fn number_mode(&self) -> NumberMode {
+        self.context.number_mode
+    }
+
+    /// set how numbers are represented such as decimals with 32 significant digits, it is kept after reset
+    pub fn set_number_mode(&mut self, mode: NumberMode) {
+        self.context.number_mode = mode;
+    }
+
+    /// the operand on top of the execution stack, it is the last operand put or the last result computed
+    pub fn top_value(&self) -> Option<&Value> {
+        self.context.execution_stack.top_val()
+    }
+
+    /// functors and constants available to this evaluator
+    pub fn library(&self) -> &FunctionLib {
+        &self.context.library
+    }
+
+    /// used to register, replace or remove functors and constants of this evaluator only
+    pub fn library_mut(&mut self) -> &mut FunctionLib {
+        &mut self.context.library
+    }
+
+    /// clear the current evaluation but keep the variables and user functions
+    pub fn reset(&mut self) {
+        self.context.execution_stack = Stack::new();
+        self.context.call_depth = 0;
+        self.context.body_depth = 0;
+        self.op_stack.clear();
+        self.bracket_stack.clear();
+        self.input_position = 0;
+    }
+
+    /// assign a value to a variable which can be used in expressions
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.context.variables.insert(name.to_string(), value);
+    }
+
+    /// the value of a variable
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        self.context.variables.get(name).cloned()
+    }
+
+    /// all variables sorted by name
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let mut variables: Vec<(String, Value)> = self.context.variables.iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+        variables
+    }
+
+    /// remove a variable, it returns the value it had
+    pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
+        self.context.variables.remove(name)
+    }
+
+    /// remove all variables
+    pub fn clear_variables(&mut self) {
+        self.context.variables.clear();
+    }
+
+    fn eval_for_close_bracket(&mut self, token: Token) -> Result<Option<Value>, CalcError> {
+        while let Some(top) = self.pop_op() {
+            if top.0.id() == ID_OPEN_BRACKET {
+                let start = self.bracket_stack.pop().unwrap_or(0);
+                let count = self.context.execution_stack.size().saturating_sub(start);
+                if self.top_op().is_some_and(takes_argument_list) {
+                    // the brackets enclose the argument list of a function, call it right now
+                    let (functor, name) = self.pop_op().unwrap();
+                    check_arg_count(functor.as_ref(), count).map_err(|e| e.at(&name.text, name.span))?;
+                    let value = functor.execute_args(&mut self.context, count)
+                        .map_err(|e| e.at(&name.text, name.span))?;
+                    self.context.execution_stack.push_val(value);
                 }
+                return Ok(self.context.execution_stack.top_val().cloned());
             }
-            Err("No open bracket found".to_string())
-        });
-        result
+            self.execute_op(&top)?;
+        }
+        Err(CalcError::UnbalancedBracket { token: token.text, span: token.span })
     }
 
-    fn put_functor(&mut self, token: &String) -> Result<Option<f64>, String> {
-        let funtor_opt = FUNCTION_LIB.get_functor(token);
+    /// compute the current argument of a function call when a comma is put
+    fn eval_for_comma(&mut self, token: Token) -> Result<Option<Value>, CalcError> {
+        while let Some(top) = self.top_op() {
+            if top.id() == ID_OPEN_BRACKET {
+                break;
+            }
+            let top = self.pop_op().unwrap();
+            self.execute_op(&top)?;
+        }
+        // a comma is only allowed between the brackets of a function call
+        let size = self.op_stack.len();
+        let in_call = size >= 2 && self.op_stack[size - 1].0.id() == ID_OPEN_BRACKET &&
+            takes_argument_list(self.op_stack[size - 2].0.as_ref());
+        if !in_call {
+            return Err(CalcError::UnexpectedToken { token: token.text, span: token.span });
+        }
+        Ok(self.context.execution_stack.top_val().cloned())
+    }
+
+    fn put_functor(&mut self, token: Token) -> Result<Option<Value>, CalcError> {
+        let funtor_opt = self.context.get_functor(&token.text);
         if funtor_opt.is_none() {
-            return Err("No functor found".to_string());
+            return Err(CalcError::UnknownFunction { token: token.text, span: token.span });
         }
         let functor = funtor_opt.unwrap();
 
-        match self.top_op() {
-            Some(top) => {
-                if functor.id() == ID_CLOSE_BRACKET {
-                    return self.eval_for_close_bracket();
-                }
-                if top.id() == ID_OPEN_BRACKET {
-                    self.push_op(functor);
-                    return Ok(None);
-                }
-                if functor.priority() < top.priority() {
-                    self.push_op(functor);
-                    // nothing need to compute then return none
-                    Ok(None)
-                }
-                else {
-                    // compute the top functor, the result will be pushed to the stack
-                    Context::scope_current(&self.excution_context, |_| {
-                        // execute function require an execution context.
-                        // So, we must ensure it will be use the current context in stead of the default one.
-                        // By bounding it by using the scope_current function, we can ensure the current context will be used.
-                        top.execute();
-                    });
-                    if self.excution_context.borrow().error_detected {
-                        if self.excution_context.borrow().error_message.is_empty() {
-                            Err("Error".to_string())
-                        }
-                        else {
-                            return Err(self.excution_context.borrow().error_message.clone())
-                        }
-                    }
-                    else {
-                        // take away the top functor from the stack due to it is already done
-                        self.pop_op();
-
-                        // push the new functor to the stack
-                        self.push_op(functor);
-
-                        // read the result from top of the stack then return
-                        Ok(Some(self.excution_context.borrow().execution_stack.top_val().unwrap().clone()))
-                    }
-                }
+        if functor.id() == ID_CLOSE_BRACKET {
+            return self.eval_for_close_bracket(token);
+        }
+        if functor.id() == ID_COMMA {
+            return self.eval_for_comma(token);
+        }
+        if functor.id() == ID_OPEN_BRACKET {
+            self.bracket_stack.push(self.context.execution_stack.size());
+        }
+        if functor.notation() == Notation::Prefix || functor.notation() == Notation::Function {
+            // prefix functor has no left operand, so nothing can be computed before it
+            self.push_op(functor, token);
+            return Ok(None);
+        }
+
+        let mut computed = false;
+        while let Some(top) = self.top_op() {
+            if top.id() == ID_OPEN_BRACKET {
+                break;
             }
-            None => {
-                self.push_op(functor);
-                // nothing need to compute then return none
-                Ok(None)
+            if functor.priority() < top.priority() ||
+                (functor.priority() == top.priority() && functor.right_associative()) {
+                break;
+            }
+            // compute the top functor, the result will be pushed to the stack
+            let top = self.pop_op().unwrap();
+            self.execute_op(&top)?;
+            computed = true;
+        }
+
+        if functor.id() == ID_PERCENT {
+            return self.eval_for_percent(functor, token);
+        }
+
+        // push the new functor to the stack
+        self.push_op(functor, token);
+
+        if computed {
+            // read the result from top of the stack then return
+            Ok(self.context.execution_stack.top_val().cloned())
+        }
+        else {
+            // nothing need to compute then return none
+            Ok(None)
+        }
+    }
+
+    /// a percentage is computed right away, it is taken of the number before it
+    /// if it is added to or subtracted from that number such as 200 + 10%
+    fn eval_for_percent(&mut self, functor: Box<dyn Functor>, token: Token) -> Result<Option<Value>, CalcError> {
+        let mut value = functor.execute(&mut self.context).map_err(|e| e.at(&token.text, token.span))?;
+        if self.top_op().is_some_and(is_additive) {
+            if let Some(base) = self.context.execution_stack.top_val() {
+                value = percent_of(self.context.number_mode, value, base.clone())
+                    .map_err(|e| e.at(&token.text, token.span))?;
             }
         }
+        self.context.execution_stack.push_val(value.clone());
+        Ok(Some(value))
     }
 
-    fn put_operand(&mut self, token: &String) -> Result<Option<f64>, String> {        
-        token.parse::<f64>().map(|value| {
-            self.excution_context.borrow_mut().execution_stack.push_val(value);
+    fn put_operand(&mut self, token: &str, span: Span) -> Result<Option<Value>, CalcError> {        
+        Value::parse(token, self.context.number_mode).map(|value| {
+            self.context.execution_stack.push_val(value.clone());
             Some(value)
-        }).map_err(|_| "Invalid token".to_string())
+        }).ok_or_else(|| CalcError::InvalidToken { token: token.to_string(), span })
+    }
+
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::user_function::ID_USER_FUNCTION_BASE;
+    use bigdecimal::BigDecimal;
+    use num_complex::Complex64;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn engine_is_send_and_sync() {
+        assert_send_sync::<Evaluator>();
+        assert_send_sync::<Expr>();
+        assert_send_sync::<FunctionLib>();
+        assert_send_sync::<Box<dyn Functor>>();
+    }
+
+    #[test]
+    fn evaluate_in_parallel() {
+        let threads: Vec<_> = (0..8).map(|t| {
+            thread::spawn(move || {
+                let mut evaluator = Evaluator::new();
+                for i in 0..1000 {
+                    let a = (t * 1000 + i) as f64;
+                    let expression = format!("{} * (3 + {}) - 2^3 / 4", a, i);
+                    let expected = a * (3.0 + i as f64) - 2.0;
+                    assert_eq!(evaluator.evaluate_str(&expression), Ok(Value::Number(expected)), "{}", expression);
+                }
+            })
+        }).collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn assign_and_use_variables() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("x = 3"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluator.evaluate_str("y = x * 2"), Ok(Value::Number(6.0)));
+        assert_eq!(evaluator.evaluate_str("x + y"), Ok(Value::Number(9.0)));
+        assert_eq!(evaluator.variables(), vec![("x".to_string(), Value::Number(3.0)), ("y".to_string(), Value::Number(6.0))]);
+
+        assert_eq!(evaluator.remove_variable("x"), Some(Value::Number(3.0)));
+        assert_eq!(evaluator.evaluate_str("x + 1"),
+            Err(CalcError::UnknownVariable { token: "x".to_string(), span: Span::new(0, 1) }));
+        assert!(matches!(evaluator.evaluate_str("sin = 1"), Err(CalcError::InvalidAssignment { .. })));
+    }
+
+    #[test]
+    fn define_and_call_user_functions() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_variable("x", Value::Number(10.0));
+        assert_eq!(evaluator.execute_str("f(x, y) = x^2 + y"), Ok(None));
+        assert_eq!(evaluator.execute_str("g(x) = f(x, 1) * 2"), Ok(None));
+        assert_eq!(evaluator.evaluate_str("g(3) + x"), Ok(Value::Number(30.0)));
+        assert_eq!(evaluator.functions().len(), 2);
+
+        assert!(matches!(evaluator.evaluate_str("f(1)"), Err(CalcError::ArgumentCount { expected: 2, found: 1, .. })));
+        assert!(matches!(evaluator.execute_str("sin(x) = x"), Err(CalcError::InvalidAssignment { .. })));
+
+        assert_eq!(evaluator.execute_str("h(x) = h(x) + 1"), Ok(None));
+        assert_eq!(evaluator.evaluate_str("1 + h(1)"),
+            Err(CalcError::RecursionLimit { token: "h".to_string(), span: Span::new(4, 5) }));
+
+        assert!(evaluator.remove_function("f").is_some());
+        assert!(matches!(evaluator.evaluate_str("g(3)"), Err(CalcError::UnknownFunction { .. })));
+    }
+
+    #[test]
+    fn reject_deeply_nested_expressions() {
+        let mut evaluator = Evaluator::new();
+        let nested = |open: &str, close: &str, count: usize| format!("{}1{}", open.repeat(count), close.repeat(count));
+        assert_eq!(evaluator.evaluate_str(&nested("(", ")", 100)), Ok(Value::Number(1.0)));
+        assert_eq!(evaluator.evaluate_str(&nested("(", ")", 5000)),
+            Err(CalcError::TooDeep { token: "(".to_string(), span: Span::new(127, 128) }));
+        assert!(matches!(evaluator.evaluate_str(&nested("-", "", 5000)), Err(CalcError::TooDeep { .. })));
+        assert!(matches!(evaluator.evaluate_str(&nested("", "+1", 5000)), Err(CalcError::TooDeep { .. })));
+        assert!(matches!(evaluator.evaluate_str(&nested("", "!", 5000)), Err(CalcError::TooDeep { .. })));
+        assert!(matches!(evaluator.evaluate_str(&nested("sqrt(", ")", 5000)), Err(CalcError::TooDeep { .. })));
+
+        // a recursive function with a large body reaches the limit before the recursion limit
+        assert_eq!(evaluator.execute_str(&format!("k(x) = {}k(x){}", "(".repeat(100), ")".repeat(100))), Ok(None));
+        assert_eq!(evaluator.evaluate_str("1 + k(1)"),
+            Err(CalcError::TooDeep { token: "k".to_string(), span: Span::new(4, 5) }));
+    }
+
+    #[test]
+    fn put_argument_lists_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        for token in ["2", "*", "max", "(", "1", "+", "2", ",", "5", ",", "4", ")", "-", "1"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(Value::Number(9.0)));
+
+        evaluator.reset();
+        evaluator.put_token(&"(".to_string()).unwrap();
+        evaluator.put_token(&"1".to_string()).unwrap();
+        assert!(matches!(evaluator.put_token(&",".to_string()), Err(CalcError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn put_logarithm_call_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        for token in ["1", "+", "log", "(", "27", ",", "3", ")"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(Value::Number(4.0)));
+    }
+
+    #[test]
+    fn put_factorial_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        for token in ["2", "*", "4", "!", "+", "1"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(Value::Number(49.0)));
+    }
+
+    #[test]
+    fn put_percentages_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        for (expression, expected) in [("200 + 10%", 220.0), ("50 * 10%", 5.0), ("2 * (100 + 50%)", 300.0), ("25%", 0.25)] {
+            evaluator.reset();
+            for token in tokenize(expression) {
+                evaluator.put_token(&token.text).unwrap();
+            }
+            assert_eq!(evaluator.evaluate(), Ok(Value::Number(expected)), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn parse_unary_minus() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("-3^2"), Ok(Value::Number(-9.0)));
+        assert_eq!(evaluator.evaluate_str("2*-3"), Ok(Value::Number(-6.0)));
+        assert_eq!(evaluator.evaluate_str("2^-1 - -1 + +1"), Ok(Value::Number(2.5)));
+        assert_eq!(evaluator.evaluate_str("-(1 + 2)!"), Ok(Value::Number(-6.0)));
+        assert_eq!(evaluator.parse_str("-3^2").unwrap().display(evaluator.library()).to_string(), "-3 ^ 2");
+        assert!(matches!(evaluator.evaluate_str("2*-"), Err(CalcError::UnexpectedEnd { .. })));
+    }
+
+    #[test]
+    fn input_scientific_notation() {
+        assert!(is_decimal("6.022e23") && is_decimal("-1E-3") && is_decimal("2e+2"));
+        assert!(!is_decimal("6e") && !is_decimal("6e-") && !is_decimal("e5") && !is_decimal("1e2.5"));
+
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("6.022e23 / 2"), Ok(Value::Number(3.011e23)));
+        // e without an exponent is the constant
+        assert_eq!(evaluator.evaluate_str("2e"), Err(CalcError::UnexpectedToken { token: "e".to_string(), span: Span::new(1, 2) }));
+
+        evaluator.reset();
+        for token in ["1.5e3", "*", "2"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(Value::Number(3000.0)));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn switch_to_decimal_precision() {
+        let mut evaluator = Evaluator::new();
+        let decimal = |text| Value::Decimal(BigDecimal::from_str(text).unwrap());
+        assert_eq!(evaluator.evaluate_str("0.1 + 0.2"), Ok(Value::Number(0.30000000000000004)));
+
+        // tokens put one by one
+        evaluator.set_number_mode(NumberMode::Decimal(40));
+        for token in ["0.1", "+", "0.2", "*", "3"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(decimal("0.7")));
+
+        // literals are rounded to the precision, variables are converted to the current mode
+        evaluator.set_number_mode(NumberMode::Decimal(5));
+        assert_eq!(evaluator.evaluate_str("x = 1.234567"), Ok(decimal("1.2346")));
+        evaluator.set_number_mode(NumberMode::Float);
+        assert_eq!(evaluator.evaluate_str("x * 2"), Ok(Value::Number(2.4692)));
+    }
+
+    #[test]
+    fn put_fractions_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Rational);
+        // a fraction token is an operand
+        for token in ["-1/3", "+", "1", "/", "6"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate().ok(), Value::parse("-1/6", NumberMode::Rational));
+    }
+
+    #[test]
+    fn put_complex_numbers_token_by_token() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Complex);
+        // a complex token is an operand
+        for token in ["1 + 2i", "*", "i"] {
+            evaluator.put_token(&token.to_string()).unwrap();
+        }
+        assert_eq!(evaluator.evaluate(), Ok(Value::Complex(Complex64::new(-2.0, 1.0))));
+    }
+
+    #[test]
+    fn parse_integers_in_radix() {
+        let mut evaluator = Evaluator::new();
+        let mode = |radix| NumberMode::Integer(IntegerMode::new(radix, 64, true).unwrap());
+
+        evaluator.set_number_mode(mode(Radix::Hex));
+        assert_eq!(evaluator.evaluate_str("FF + 1"), Ok(Value::Integer(256)));
+        assert_eq!(evaluator.evaluate_str("1A * -A"), Ok(Value::Integer(-260)));
+        assert_eq!(evaluator.evaluate_str("1E3"), Ok(Value::Integer(0x1E3)));
+        assert_eq!(evaluator.evaluate_str("10 + 1"), Ok(Value::Integer(17)));
+        assert_eq!(evaluator.evaluate_str("1G"), Err(CalcError::UnexpectedToken { token: "G".to_string(), span: Span::new(1, 2) }));
+
+        evaluator.set_number_mode(mode(Radix::Oct));
+        assert_eq!(evaluator.evaluate_str("17 + 1"), Ok(Value::Integer(16)));
+        assert_eq!(evaluator.evaluate_str("18"), Err(CalcError::InvalidToken { token: "18".to_string(), span: Span::new(0, 2) }));
+
+        evaluator.set_number_mode(mode(Radix::Bin));
+        assert_eq!(evaluator.evaluate_str("101 * 11"), Ok(Value::Integer(15)));
+        assert_eq!(evaluator.evaluate_str("1 + 2"), Err(CalcError::InvalidToken { token: "2".to_string(), span: Span::new(4, 5) }));
+    }
+
+    /// number of items of a list, the kind of the argument is checked by the functor itself
+    struct Len {}
+    impl Functor for Len {
+        fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+            match context.execution_stack.pop_val() {
+                Some(Value::List(items)) => Ok(Value::Integer(items.len() as i128)),
+                Some(value) => Err(CalcError::type_error(ValueKind::List, value.kind())),
+                None => Err(CalcError::stack_underflow()),
+            }
+        }
+        fn priority(&self) -> i32 {
+            PRIORITY_USER_FUNCTION
+        }
+        fn id(&self) -> FunctionId {
+            ID_REGISTERED_FUNCTION_BASE
+        }
+        fn arg_count(&self) -> i32 {
+            1
+        }
+        fn notation(&self) -> Notation {
+            Notation::Function
+        }
+    }
+
+    #[test]
+    fn compute_values_of_other_kinds() {
+        let mut evaluator = Evaluator::new();
+        let library = evaluator.library_mut();
+        library.register_functor("len", |_| Box::new(Len {}));
+        library.register_constant_creator("names", |_| Some(Value::List(vec![Value::String("a".into()), Value::String("b".into())])));
+        library.register_constant_creator("yes", |_| Some(Value::Boolean(true)));
+        library.register_constant_creator("big", |_| Some(Value::Integer(i64::MAX.into())));
+
+        assert_eq!(evaluator.evaluate_str("names"), Ok(Value::List(vec![Value::String("a".into()), Value::String("b".into())])));
+        assert_eq!(evaluator.evaluate_str("len(names) + 0.5"), Ok(Value::Number(2.5)));
+        assert_eq!(evaluator.evaluate_str("yes"), Ok(Value::Boolean(true)));
+
+        // functors report the kind they expect
+        let error = evaluator.evaluate_str("1 + sin yes").unwrap_err();
+        assert_eq!(error.to_string(), "sin requires a value of type number but boolean given");
+        assert_eq!(error.span(), Some(Span::new(4, 7)));
+        assert!(matches!(evaluator.evaluate_str("names * 2"),
+            Err(CalcError::TypeError { expected: ValueKind::Number, found: ValueKind::List, .. })));
+        assert!(matches!(evaluator.evaluate_str("len(big)"),
+            Err(CalcError::TypeError { expected: ValueKind::List, found: ValueKind::Integer, .. })));
+
+        // integers are numbers in every number mode and stay exact in rational mode
+        evaluator.set_number_mode(NumberMode::Rational);
+        assert_eq!(evaluator.evaluate_str("big * 2 + 2").ok(), Value::parse("18446744073709551616", NumberMode::Rational));
+        evaluator.set_number_mode(NumberMode::Decimal(32));
+        assert_eq!(evaluator.evaluate_str("big + 1"), Ok(Value::Decimal(BigDecimal::from(1u64 << 63))));
+
+        // variables keep values of any kind after the number mode is changed
+        evaluator.execute_str("x = names").unwrap();
+        evaluator.set_number_mode(NumberMode::Float);
+        assert_eq!(evaluator.evaluate_str("len(x)"), Ok(Value::Integer(2)));
+    }
+
+    #[test]
+    fn register_functions_per_evaluator() {
+        let scale = 10.0;
+        let mut stats = FunctionLib::empty();
+        stats.register("mean", 3, PRIORITY_USER_FUNCTION, Associativity::Left, |args| Ok(args.iter().sum::<f64>() / 3.0));
+        stats.register_constant("n", 3.0);
+
+        let mut evaluator = Evaluator::new();
+        let library = evaluator.library_mut();
+        library.register("scaled", 1, PRIORITY_UNARY_OP, Associativity::Left, move |args| Ok(args[0] * scale));
+        library.register("↑", 2, PRIORITY_POWER, Associativity::Right, |args| Ok(args[0].powf(args[1])));
+        library.register("sin", 1, PRIORITY_UNARY_OP, Associativity::Left, |args| Ok(args[0] + 1.0));
+        library.import("stats", &stats);
+
+        assert_eq!(evaluator.evaluate_str("scaled 2 + scaled(1)"), Ok(Value::Number(30.0)));
+        assert_eq!(evaluator.evaluate_str("2↑3↑2"), Ok(Value::Number(512.0)));
+        // functors registered here are written as they are parsed
+        evaluator.library_mut().register_functor("‰", |_: &String| -> Box<dyn Functor> { Box::new(Percent{}) });
+        let expr = evaluator.parse_str("scaled 2‰").unwrap();
+        assert_eq!(expr.display(evaluator.library()).to_string(), "scaled 2‰");
+        assert_eq!(evaluator.evaluate_expr(&expr), Ok(Value::Number(0.2)));
+        assert_eq!(evaluator.evaluate_str("sin 1"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluator.evaluate_str("stats.mean(1, 2, stats.n) * 2"), Ok(Value::Number(4.0)));
+        assert!(matches!(evaluator.evaluate_str("mean(1, 2, 3)"), Err(CalcError::UnknownFunction { .. })));
+
+        assert!(evaluator.library_mut().unregister("scaled"));
+        assert!(matches!(evaluator.evaluate_str("scaled 2"), Err(CalcError::UnexpectedToken { .. })));
+
+        // other evaluators keep the built-in library
+        assert_eq!(Evaluator::new().evaluate_str("sin 0"), Ok(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn registered_and_user_functions_have_distinct_ids() {
+        let mut evaluator = Evaluator::new();
+        let ids: Vec<FunctionId> = (0..1000).map(|i| {
+            evaluator.library_mut().register(&format!("f{}", i), 1, PRIORITY_UNARY_OP, Associativity::Left, move |args| Ok(args[0] + i as f64))
+        }).collect();
+        assert!(ids.iter().all(|&id| id < ID_USER_FUNCTION_BASE));
+
+        assert_eq!(evaluator.execute_str("g(x) = f999 x"), Ok(None));
+        let id = evaluator.context.get_functor(&"g".to_string()).map(|f| f.id());
+        assert!(id.is_some_and(|id| !ids.contains(&id)));
+        assert_eq!(evaluator.evaluate_str("g(1)"), Ok(Value::Number(1000.0)));
+    }
+
+    #[test]
+    fn share_parsed_expression_between_threads() {
+        let expr = Arc::new(Evaluator::new().parse_str("√16 + 3² * (1 + 1)").unwrap());
+        let threads: Vec<_> = (0..8).map(|_| {
+            let expr = Arc::clone(&expr);
+            thread::spawn(move || {
+                let mut evaluator = Evaluator::new();
+                for _ in 0..1000 {
+                    assert_eq!(evaluator.evaluate_expr(&expr), Ok(Value::Number(22.0)));
+                }
+            })
+        }).collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+}
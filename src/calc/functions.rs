@@ -1,6 +1,14 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use lazy_static::lazy_static;
-use crate::calc::context::Context;
+use bigdecimal::{BigDecimal, One, RoundingMode, Signed, ToPrimitive, Zero};
+use bigdecimal::num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use crate::calc::context::{Context, Stack};
+use crate::calc::error::{CalcError, Span};
+use crate::calc::value::{NumberMode, Value, ValueKind};
+use crate::calc::decimal;
 
 pub use usize as FunctionId;
 // all function ids, function id must be index of corresponding function in ALL_FUNCTIONS
@@ -21,28 +29,140 @@ pub const ID_OPEN_BRACKET: FunctionId = 13;
 pub const ID_CLOSE_BRACKET: FunctionId = 14;
 pub const ID_SQR: FunctionId = 15;
 pub const ID_INV: FunctionId = 16;
+pub const ID_COMMA: FunctionId = 17;
+pub const ID_MAX: FunctionId = 18;
+pub const ID_MIN: FunctionId = 19;
+pub const ID_SUM: FunctionId = 20;
+pub const ID_AVG: FunctionId = 21;
+pub const ID_HYPOT: FunctionId = 22;
+pub const ID_ROUND: FunctionId = 23;
+pub const ID_ASIN: FunctionId = 24;
+pub const ID_ACOS: FunctionId = 25;
+pub const ID_ATAN: FunctionId = 26;
+pub const ID_ATAN2: FunctionId = 27;
+pub const ID_SINH: FunctionId = 28;
+pub const ID_COSH: FunctionId = 29;
+pub const ID_TANH: FunctionId = 30;
+pub const ID_ASINH: FunctionId = 31;
+pub const ID_ACOSH: FunctionId = 32;
+pub const ID_ATANH: FunctionId = 33;
+pub const ID_LOG10: FunctionId = 34;
+pub const ID_LOG2: FunctionId = 35;
+pub const ID_LOG: FunctionId = 36;
+pub const ID_EXP: FunctionId = 37;
+pub const ID_EXP10: FunctionId = 38;
+pub const ID_FACTORIAL: FunctionId = 39;
+pub const ID_GAMMA: FunctionId = 40;
+pub const ID_PERMUTATION: FunctionId = 41;
+pub const ID_COMBINATION: FunctionId = 42;
+pub const ID_PERCENT: FunctionId = 43;
+/// ids of functions registered at runtime start from here, they are below `ID_USER_FUNCTION_BASE`
+pub const ID_REGISTERED_FUNCTION_BASE: FunctionId = 100;
+
+// a lower priority binds tighter
+/// priority of + and -
+pub const PRIORITY_ADDITIVE: i32 = 6;
+/// priority of *, / and mod
+pub const PRIORITY_MULTIPLICATIVE: i32 = 5;
+/// priority of ^
+pub const PRIORITY_POWER: i32 = 4;
+/// priority of functions called with an argument list such as max(1, 2)
+pub const PRIORITY_USER_FUNCTION: i32 = 2;
+/// priority of prefix and postfix functors such as sin and ²
+pub const PRIORITY_UNARY_OP: i32 = 3;
+
+/// largest exponent of an integer power computed exactly in rational mode
+const MAX_EXACT_EXPONENT: u32 = 100000;
+/// Where a functor is written relative to its arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// before its only argument such as sin 1
+    Prefix,
+    /// between its two arguments such as 1 + 2
+    Infix,
+    /// after its only argument such as 3²
+    Postfix,
+    /// followed by a bracketed argument list such as f(1, 2)
+    Function,
+}
 
-const PRIODITY_ADDITIVE: i32 = 6;
-const PRIODITY_MULTIPLICATIVE: i32 = 5;
-const PRIODITY_USER_FUNCTION: i32 = 2;
-const PRIODITY_UNARY_OP: i32 = 3;
 /// A trait for a function that can be executed.
-pub trait Functor {
-    fn execute(&self);
+/// Functors keep no state between executions, so they can be shared between threads.
+/// They are added to a library by [`FunctionLib::register_functor`],
+/// the arguments are popped from the execution stack of the [`Context`] in the reverse order.
+///
+/// ```
+/// use rust_caculator::{CalcError, Context, Evaluator, FunctionId, Functor, Value, PRIORITY_USER_FUNCTION};
+///
+/// struct Hypot;
+/// impl Functor for Hypot {
+///     fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+///         let b = context.execution_stack.pop_val().ok_or_else(CalcError::stack_underflow)?.to_f64();
+///         let a = context.execution_stack.pop_val().ok_or_else(CalcError::stack_underflow)?.to_f64();
+///         Ok(Value::Number(a.hypot(b)))
+///     }
+///     fn priority(&self) -> i32 { PRIORITY_USER_FUNCTION }
+///     fn id(&self) -> FunctionId { 500 }
+///     fn arg_count(&self) -> i32 { 2 }
+/// }
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.library_mut().register_functor("hypot", |_: &String| -> Box<dyn Functor> { Box::new(Hypot) });
+/// assert_eq!(evaluator.evaluate_str("hypot(3, 4)"), Ok(Value::Number(5.0)));
+/// ```
+pub trait Functor: Send + Sync {
+    /// pop the arguments from the execution stack of the context then return the result without pushing it
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError>;
+    /// how tightly the functor binds its arguments, a lower priority binds tighter
     fn priority(&self) -> i32;
+    /// identifies the kind of functor, such as ID_SUB for both binary and unary minus
     fn id(&self) -> FunctionId;
+    /// number of arguments, it is the least number of arguments for a function accepting a variable number of them
     fn arg_count(&self) -> i32;
+    /// the most number of arguments, none if there is no limit
+    fn max_arg_count(&self) -> Option<usize> {
+        Some(self.arg_count() as usize)
+    }
+    /// execute the functor with the given number of arguments on the execution stack,
+    /// the number is already checked by check_arg_count
+    fn execute_args(&self, context: &mut Context, _count: usize) -> Result<Value, CalcError> {
+        self.execute(context)
+    }
+    /// operators with the same priority are grouped from right to left when it returns true
+    fn right_associative(&self) -> bool {
+        false
+    }
+    /// where the functor is written, a functor is prefix, infix or a function by its number of arguments unless it says otherwise
+    fn notation(&self) -> Notation {
+        match self.arg_count() {
+            1 => Notation::Prefix,
+            2 => Notation::Infix,
+            _ => Notation::Function,
+        }
+    }
+}
+
+/// postfix functor is written after its operand such as x²
+pub fn is_postfix(functor: &dyn Functor) -> bool {
+    functor.notation() == Notation::Postfix
+}
+
+/// check whether the functor accepts the given number of arguments, the error is not bound to any token
+pub fn check_arg_count(functor: &dyn Functor, count: usize) -> Result<(), CalcError> {
+    let least = functor.arg_count() as usize;
+    let most = functor.max_arg_count();
+    if count < least || most.is_some_and(|most| count > most) {
+        let expected = if count < least { least } else { most.unwrap() };
+        return Err(CalcError::ArgumentCount { token: String::new(), span: Span::default(), expected, found: count });
+    }
+    Ok(())
 }
 
 /// open bracket
 pub struct OpenBracket {}
 impl Functor for OpenBracket {
-    fn execute(&self) {
-        Context::with_current(|c| {
-            let mut t = c.borrow_mut();
-            t.error_detected = true;
-            t.error_message = "Open bracket is not a function".to_string();
-        });
+    fn execute(&self, _: &mut Context) -> Result<Value, CalcError> {
+        Err(CalcError::UnbalancedBracket { token: String::new(), span: Default::default() })
     }
     fn priority(&self) -> i32 {
         0
@@ -55,15 +175,28 @@ impl Functor for OpenBracket {
     }
 }
 
+/// separator of arguments such as the comma in max(1, 2)
+pub struct Comma {}
+impl Functor for Comma {
+    fn execute(&self, _: &mut Context) -> Result<Value, CalcError> {
+        Err(CalcError::UnexpectedToken { token: String::new(), span: Default::default() })
+    }
+    fn priority(&self) -> i32 {
+        999
+    }
+    fn id(&self) -> FunctionId {
+        ID_COMMA
+    }
+    fn arg_count(&self) -> i32 {
+        0
+    }
+}
+
 /// close bracket
 pub struct CloseBracket {}
 impl Functor for CloseBracket {
-    fn execute(&self) {
-        Context::with_current(|c| {
-            let mut t = c.borrow_mut();
-            t.error_detected = true;
-            t.error_message = "Close bracket is not a function".to_string();
-        });
+    fn execute(&self, _: &mut Context) -> Result<Value, CalcError> {
+        Err(CalcError::UnbalancedBracket { token: String::new(), span: Default::default() })
     }
     fn priority(&self) -> i32 {
         999
@@ -76,141 +209,494 @@ impl Functor for CloseBracket {
     }
 }
 
-/// A trait for a function with only one parameter
+/// an infinite result computed from finite arguments means the value is out of range of f64
+fn check_overflow(result: f64, args: &[f64]) -> Result<f64, CalcError> {
+    if result.is_infinite() && args.iter().all(|a| a.is_finite()) {
+        Err(CalcError::overflow())
+    } else {
+        Ok(result)
+    }
+}
+
+/// pop the given number of arguments from the stack, the first argument is the deepest one
+fn pop_args(stack: &mut Stack, count: usize) -> Result<Vec<Value>, CalcError> {
+    if stack.size() < count {
+        return Err(CalcError::stack_underflow());
+    }
+    let mut args = Vec::new();
+    for _ in 0..count {
+        args.push(stack.pop_val().unwrap());
+    }
+    args.reverse();
+    Ok(args)
+}
+
+/// arithmetic functors take only numbers, a value of any other kind is a type error
+fn check_numbers(args: &[Value]) -> Result<(), CalcError> {
+    match args.iter().find(|arg| !arg.kind().is_number()) {
+        Some(arg) => Err(CalcError::type_error(ValueKind::Number, arg.kind())),
+        None => Ok(()),
+    }
+}
+
+/// pop the given number of arguments which must be numbers, the first argument is the deepest one
+fn pop_numbers(stack: &mut Stack, count: usize) -> Result<Vec<Value>, CalcError> {
+    let args = pop_args(stack, count)?;
+    check_numbers(&args)?;
+    Ok(args)
+}
+
+/// compute a result in the number mode of the context,
+/// either with f64 or with decimals which are rounded to the precision of the mode,
+/// results which are not exact fractions in rational mode or in programmer mode are computed with f64
+fn compute_value<F, D>(mode: NumberMode, args: &[Value], float: F, decimal: D) -> Result<Value, CalcError>
+where
+    F: FnOnce(&[f64]) -> Result<f64, CalcError>,
+    D: FnOnce(&[BigDecimal], u64) -> Result<BigDecimal, CalcError>,
+{
+    match mode {
+        NumberMode::Float | NumberMode::Rational => {
+            let args: Vec<f64> = args.iter().map(Value::to_f64).collect();
+            float(&args).and_then(|v| check_overflow(v, &args)).map(Value::Number)
+        }
+        // the result such as √10 is truncated to an integer of the word
+        NumberMode::Integer(_) => {
+            let args: Vec<f64> = args.iter().map(Value::to_f64).collect();
+            float(&args).and_then(|v| check_overflow(v, &args)).and_then(|v| Value::from_f64(v, mode))
+        }
+        NumberMode::Decimal(precision) => {
+            let args = args.iter().map(|arg| arg.to_decimal(precision)).collect::<Result<Vec<_>, _>>()?;
+            decimal(&args, precision).map(|v| Value::Decimal(decimal::round(&v, precision)))
+        }
+        NumberMode::Complex => compute_complex_value(args, float, |_| None),
+    }
+}
+
+/// compute a complex result in complex mode. Real arguments are computed with f64 first so that real results
+/// are the same as in float mode, the complex function is used for complex arguments and for real arguments
+/// out of the domain of the real function such as √-1, it is none if the function is only defined for real numbers
+fn compute_complex_value<F, C>(args: &[Value], float: F, complex: C) -> Result<Value, CalcError>
+where
+    F: FnOnce(&[f64]) -> Result<f64, CalcError>,
+    C: FnOnce(&[Complex64]) -> Option<Result<Complex64, CalcError>>,
+{
+    let args: Vec<Complex64> = args.iter().map(Value::to_complex).collect();
+    let real_args: Option<Vec<f64>> = args.iter().map(|a| (a.im == 0.0).then_some(a.re)).collect();
+    let real = real_args.map(|real_args| float(&real_args).and_then(|v| check_overflow(v, &real_args)));
+    let real_error = match real {
+        Some(Ok(v)) => return Ok(Value::Complex(Complex64::from(v))),
+        Some(Err(e @ CalcError::DomainError { .. })) => Some(e),
+        Some(Err(e)) => return Err(e),
+        None => None,
+    };
+    match complex(&args) {
+        Some(Ok(v)) if v.is_finite() => Ok(Value::Complex(v)),
+        // the result is not finite in the complex plane either such as ln 0
+        Some(Ok(_)) => Err(real_error.unwrap_or_else(CalcError::overflow)),
+        Some(Err(e)) => Err(e),
+        None => Err(real_error.unwrap_or_else(|| CalcError::domain_error("The function is only defined for real numbers"))),
+    }
+}
+
+/// compute an exact fraction in rational mode when all arguments are fractions,
+/// in programmer mode the exact result is truncated to an integer of the word such as 7 / 2 = 3,
+/// none if the result has to be computed by compute_value instead
+fn compute_exact<R>(mode: NumberMode, args: &[Value], rational: R) -> Option<Result<Value, CalcError>>
+where
+    R: FnOnce(&[BigRational]) -> Option<Result<BigRational, CalcError>>,
+{
+    if !matches!(mode, NumberMode::Rational | NumberMode::Integer(_)) {
+        return None;
+    }
+    let args = args.iter().map(Value::to_rational).collect::<Option<Vec<_>>>()?;
+    let result = rational(&args)?.map(Value::Rational);
+    match mode {
+        NumberMode::Integer(_) => Some(result.and_then(|v| v.to_mode(mode))),
+        _ => Some(result),
+    }
+}
+
+/// compute with decimals when all arguments are integers, used by functions of integers such as factorial
+fn compute_rational_by_decimal<D>(args: &[BigRational], decimal: D) -> Option<Result<BigRational, CalcError>>
+where
+    D: FnOnce(&[BigDecimal]) -> Result<BigDecimal, CalcError>,
+{
+    if !args.iter().all(BigRational::is_integer) {
+        return None;
+    }
+    let args: Vec<BigDecimal> = args.iter().map(|a| BigDecimal::from(a.to_integer())).collect();
+    Some(decimal(&args).and_then(|v| decimal::to_rational(&v).ok_or_else(CalcError::overflow)))
+}
+
+/// compute with f64 then convert the result to a decimal, used by functions which have no decimal implementation
+fn compute_decimal_by_f64<F>(args: &[BigDecimal], float: F) -> Result<BigDecimal, CalcError>
+where
+    F: FnOnce(&[f64]) -> Result<f64, CalcError>,
+{
+    let args: Vec<f64> = args.iter().map(decimal::to_f64).collect();
+    float(&args).and_then(|v| check_overflow(v, &args)).and_then(decimal::from_f64)
+}
+
+/// compute the result of a functor with the hook of the number mode, UnaryFunctor, BinaryFunctor and NaryFunctor
+/// are executed by it and have the same hooks for one, two or any number of arguments:
+/// - `compute` computes with f64 in float mode and in the modes whose hooks have no result
+/// - `compute_decimal` computes with decimals to the given number of significant digits,
+///   by default it is computed with f64 so only about 16 digits are correct
+/// - `compute_rational` computes an exact fraction in rational and programmer modes,
+///   it is none by default or if the result is not a fraction so it is computed with f64
+/// - `compute_complex` computes with complex numbers in complex mode,
+///   it is none by default or if the function is only defined for real numbers
+fn compute_in_mode<F, D, R, C>(mode: NumberMode, args: &[Value], float: F, decimal: D, rational: R, complex: C)
+    -> Result<Value, CalcError>
+where
+    F: FnOnce(&[f64]) -> Result<f64, CalcError>,
+    D: FnOnce(&[BigDecimal], u64) -> Result<BigDecimal, CalcError>,
+    R: FnOnce(&[BigRational]) -> Option<Result<BigRational, CalcError>>,
+    C: FnOnce(&[Complex64]) -> Option<Result<Complex64, CalcError>>,
+{
+    if let Some(result) = compute_exact(mode, args, rational) {
+        return result;
+    }
+    if mode == NumberMode::Complex {
+        return compute_complex_value(args, float, complex);
+    }
+    compute_value(mode, args, float, decimal)
+}
+
+/// A trait for a function with only one parameter, its hooks are described by compute_in_mode
 pub trait UnaryFunctor : Functor {
-    fn compute(&self, a: f64) -> Result<f64, &str>;
-    fn execute(&self) {
-        Context::with_current(|c| {
-            let mut t = c.borrow_mut();
-            if t.execution_stack.size() < 1 {
-                t.error_detected = true;
-                return;
-            }
-            let a = t.execution_stack.pop_val().unwrap();
-            let result = self.compute(a);
-            match result {
-                Ok(v) => t.execution_stack.push_val(v),
-                Err(s) => {
-                    t.error_message = s.to_string();
-                    t.error_detected = true;
-                }
-            }
-        });
+    fn compute(&self, a: f64) -> Result<f64, CalcError>;
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        compute_decimal_by_f64(std::slice::from_ref(a), |args| self.compute(args[0]))
+    }
+    fn compute_rational(&self, _a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        None
+    }
+    fn compute_complex(&self, _a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        None
+    }
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, 1)?;
+        compute_in_mode(context.number_mode, &args,
+            |args| self.compute(args[0]),
+            |args, precision| self.compute_decimal(&args[0], precision),
+            |args| self.compute_rational(&args[0]),
+            |args| self.compute_complex(args[0]))
     }
 }
 
-/// A trait for a function with two parameters
+/// A trait for a function with two parameters, its hooks are described by compute_in_mode
 pub trait BinaryFunctor {
-    fn compute(&self, a: f64, b: f64) -> Result<f64, &str>;
-    fn execute(&self) {
-        Context::with_current(|c| {
-            let mut t = c.borrow_mut();
-            if t.execution_stack.size() < 2 {
-                t.error_detected = true;
-                return;
-            }
-            let b = t.execution_stack.pop_val().unwrap();
-            let a = t.execution_stack.pop_val().unwrap();
-            let result = self.compute(a, b);
-            match result {
-                Ok(v) => t.execution_stack.push_val(v),
-                Err(s) => {
-                    t.error_message = s.to_string();
-                    t.error_detected = true;
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError>;
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        compute_decimal_by_f64(&[a.clone(), b.clone()], |args| self.compute(args[0], args[1]))
+    }
+    fn compute_rational(&self, _a: &BigRational, _b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        None
+    }
+    fn compute_complex(&self, _a: Complex64, _b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        None
+    }
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, 2)?;
+        compute_in_mode(context.number_mode, &args,
+            |args| self.compute(args[0], args[1]),
+            |args, precision| self.compute_decimal(&args[0], &args[1], precision),
+            |args| self.compute_rational(&args[0], &args[1]),
+            |args| self.compute_complex(args[0], args[1]))
+    }
+}
+
+/// A trait for a function with a variable number of parameters such as max(1, 2, 3),
+/// its hooks are described by compute_in_mode
+pub trait NaryFunctor : Functor {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError>;
+    fn compute_decimal(&self, args: &[BigDecimal], _precision: u64) -> Result<BigDecimal, CalcError> {
+        compute_decimal_by_f64(args, |args| self.compute(args))
+    }
+    fn compute_rational(&self, _args: &[BigRational]) -> Option<Result<BigRational, CalcError>> {
+        None
+    }
+    fn compute_complex(&self, _args: &[Complex64]) -> Option<Result<Complex64, CalcError>> {
+        None
+    }
+    fn execute(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, count)?;
+        compute_in_mode(context.number_mode, &args,
+            |args| self.compute(args),
+            |args, precision| self.compute_decimal(args, precision),
+            |args| self.compute_rational(args),
+            |args| self.compute_complex(args))
+    }
+}
+
+/// A trait for a trigonometric function of an angle given in the angle mode of the context
+pub trait AngleFunctor : Functor {
+    fn compute(&self, radians: f64) -> Result<f64, CalcError>;
+    /// compute with decimals to the given number of significant digits
+    fn compute_decimal(&self, radians: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError>;
+    /// compute with complex numbers in complex mode
+    fn compute_complex(&self, radians: Complex64) -> Complex64;
+    /// exact result for an angle of the given number of right angles, the number is in 0..4
+    fn compute_right_angles(&self, quarters: u8) -> Result<f64, CalcError>;
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, 1)?;
+        let angle_mode = context.angle_mode;
+        let float = |args: &[f64]| match angle_mode.right_angles(args[0]) {
+            Some(quarters) => self.compute_right_angles(quarters),
+            None => self.compute(angle_mode.to_radians(args[0])),
+        };
+        if context.number_mode == NumberMode::Complex {
+            return compute_complex_value(&args, float,
+                |args| Some(Ok(self.compute_complex(angle_mode.to_radians_complex(args[0])))));
+        }
+        compute_value(context.number_mode, &args, float,
+            |args, precision| match angle_mode.right_angles_decimal(&args[0]) {
+                Some(quarters) => self.compute_right_angles(quarters).and_then(decimal::from_f64),
+                None => {
+                    let radians = angle_mode.to_radians_decimal(&args[0], precision + decimal::GUARD_DIGITS);
+                    self.compute_decimal(&radians, precision)
                 }
-            }
-        });
+            })
+    }
+}
+
+/// A trait for an inverse trigonometric function, its result is an angle in the angle mode of the context
+pub trait InverseAngleFunctor : Functor {
+    /// return the angle in radians
+    fn compute(&self, a: f64) -> Result<f64, CalcError>;
+    /// return the angle in radians computed with decimals to the given number of significant digits
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError>;
+    /// return the angle in radians computed with complex numbers in complex mode
+    fn compute_complex(&self, a: Complex64) -> Complex64;
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, 1)?;
+        let angle_mode = context.angle_mode;
+        let float = |args: &[f64]| self.compute(args[0]).map(|radians| angle_mode.from_radians(radians));
+        if context.number_mode == NumberMode::Complex {
+            return compute_complex_value(&args, float,
+                |args| Some(Ok(angle_mode.from_radians_complex(self.compute_complex(args[0])))));
+        }
+        compute_value(context.number_mode, &args, float,
+            |args, precision| {
+                let radians = self.compute_decimal(&args[0], precision + decimal::GUARD_DIGITS)?;
+                Ok(angle_mode.from_radians_decimal(&radians, precision + decimal::GUARD_DIGITS))
+            })
     }
 }
+
 /// Add function
 pub struct Add {
 }
 impl Functor for Add {
-    fn execute(&self) {
-        BinaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_ADD
     }
     fn priority(&self) -> i32 {
-        PRIODITY_ADDITIVE
+        PRIORITY_ADDITIVE
     }
     fn arg_count(&self) -> i32 {
         2
     }
 }
 impl BinaryFunctor for Add {
-    fn compute(&self, a: f64, b: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
         Ok(a + b)
     }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a + b)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a + b))
+    }
+    fn compute_complex(&self, a: Complex64, b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a + b))
+    }
 }
 
 /// Sub function
 pub struct Sub {
 }
 impl Functor for Sub {
-    fn execute(&self) {
-        BinaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_SUB
     }
     fn priority(&self) -> i32 {
-        PRIODITY_ADDITIVE
+        PRIORITY_ADDITIVE
     }
     fn arg_count(&self) -> i32 {
         2
     }
 }
 impl BinaryFunctor for Sub {
-    fn compute(&self, a: f64, b: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
         Ok(a - b)
     }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a - b)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a - b))
+    }
+    fn compute_complex(&self, a: Complex64, b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a - b))
+    }
 }
 
 /// Mul function
 pub struct Mul {
 }
 impl Functor for Mul {
-    fn execute(&self) {
-        BinaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_MUL
     }
     fn priority(&self) -> i32 {
-        PRIODITY_MULTIPLICATIVE
+        PRIORITY_MULTIPLICATIVE
     }
     fn arg_count(&self) -> i32 {
         2
     }
 }
 impl BinaryFunctor for Mul {
-    fn compute(&self, a: f64, b: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
         Ok(a * b)
     }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a * b)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a * b))
+    }
+    fn compute_complex(&self, a: Complex64, b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a * b))
+    }
 }
 
 /// Div function
 pub struct Div {
 }
 impl Functor for Div {
-    fn execute(&self) {
-        BinaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_DIV
     }
     fn priority(&self) -> i32 {
-        PRIODITY_MULTIPLICATIVE
+        PRIORITY_MULTIPLICATIVE
     }
     fn arg_count(&self) -> i32 {
         2
     }
 }
 impl BinaryFunctor for Div {
-    fn compute(&self, a: f64, b: f64) -> Result<f64, &str> {
-        if b == 0.0 {Err("divide to zero")} else {Ok(a / b)}
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        if b == 0.0 {Err(CalcError::division_by_zero())} else {Ok(a / b)}
+    }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::div(a, b, precision)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(if b.is_zero() {Err(CalcError::division_by_zero())} else {Ok(a / b)})
+    }
+    fn compute_complex(&self, a: Complex64, b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(if b.norm_sqr() == 0.0 {Err(CalcError::division_by_zero())} else {Ok(a / b)})
+    }
+}
+
+/// Mod function
+pub struct Mod {
+}
+impl Functor for Mod {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_MOD
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_MULTIPLICATIVE
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+}
+impl BinaryFunctor for Mod {
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        if b == 0.0 {Err(CalcError::division_by_zero())} else {Ok(a % b)}
+    }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::rem(a, b)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(if b.is_zero() {Err(CalcError::division_by_zero())} else {Ok(a % b)})
+    }
+}
+
+/// Pow function
+pub struct Pow {
+}
+impl Functor for Pow {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_POW
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_POWER
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+    fn right_associative(&self) -> bool {
+        true
+    }
+}
+impl BinaryFunctor for Pow {
+    fn compute(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        if a == 0.0 && b < 0.0 {
+            return Err(CalcError::division_by_zero());
+        }
+        let res = a.powf(b);
+        if res.is_nan() {
+            Err(CalcError::domain_error("Power of negative number is undefined"))
+        } else {
+            Ok(res)
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::pow(a, b, precision)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        // only an integer power of a fraction is a fraction, a huge power is computed with f64 so it does not take forever
+        let exponent = b.is_integer().then(|| b.to_integer().to_i32())??;
+        if exponent.unsigned_abs() > MAX_EXACT_EXPONENT {
+            return None;
+        }
+        if a.is_zero() && exponent < 0 {
+            return Some(Err(CalcError::division_by_zero()));
+        }
+        Some(Ok(a.pow(exponent)))
+    }
+    fn compute_complex(&self, a: Complex64, b: Complex64) -> Option<Result<Complex64, CalcError>> {
+        if a.norm_sqr() == 0.0 {
+            return Some(Err(CalcError::domain_error("Power of zero to a complex number is undefined")));
+        }
+        // an integer power is computed by multiplications so that (1 + i)^2 is exactly 2i
+        if b.im == 0.0 && b.re.fract() == 0.0 && b.re.abs() <= i32::MAX as f64 {
+            return Some(Ok(a.powi(b.re as i32)));
+        }
+        Some(Ok(a.powc(b)))
     }
 }
 
@@ -218,178 +704,1918 @@ impl BinaryFunctor for Div {
 pub struct Sin {
 }
 impl Functor for Sin {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        AngleFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_SIN
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
 }
-impl UnaryFunctor for Sin {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+impl AngleFunctor for Sin {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         Ok(a.sin())
     }
+    fn compute_right_angles(&self, quarters: u8) -> Result<f64, CalcError> {
+        Ok([0.0, 1.0, 0.0, -1.0][quarters as usize])
+    }
+    fn compute_decimal(&self, radians: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::sin_cos(radians, precision).map(|(sin, _)| sin)
+    }
+    fn compute_complex(&self, radians: Complex64) -> Complex64 {
+        radians.sin()
+    }
 }
 
 /// cos function
 pub struct Cos {
 }
 impl Functor for Cos {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        AngleFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_COS
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
 }
-impl UnaryFunctor for Cos {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+impl AngleFunctor for Cos {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         Ok(a.cos())
     }
+    fn compute_right_angles(&self, quarters: u8) -> Result<f64, CalcError> {
+        Ok([1.0, 0.0, -1.0, 0.0][quarters as usize])
+    }
+    fn compute_decimal(&self, radians: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::sin_cos(radians, precision).map(|(_, cos)| cos)
+    }
+    fn compute_complex(&self, radians: Complex64) -> Complex64 {
+        radians.cos()
+    }
 }
 
 /// tan function
 pub struct Tan {
 }
 impl Functor for Tan {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        AngleFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_TAN
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
 }
-impl UnaryFunctor for Tan {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+impl AngleFunctor for Tan {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         Ok(a.tan())
     }
+    fn compute_right_angles(&self, quarters: u8) -> Result<f64, CalcError> {
+        if quarters % 2 == 1 {
+            Err(CalcError::domain_error("Tangent of a right angle is undefined"))
+        } else {
+            Ok(0.0)
+        }
+    }
+    fn compute_decimal(&self, radians: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        let (sin, cos) = decimal::sin_cos(radians, precision + decimal::GUARD_DIGITS)?;
+        decimal::div(&sin, &cos, precision)
+    }
+    fn compute_complex(&self, radians: Complex64) -> Complex64 {
+        radians.tan()
+    }
 }
 
 /// sqrt function
 pub struct Sqrt {}
 impl Functor for Sqrt {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_SQRT
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
 }
 impl UnaryFunctor for Sqrt {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         if a < 0.0 {
-            Err("Square root of negative number is undefined")
+            Err(CalcError::domain_error("Square root of negative number is undefined"))
         } else {
             Ok(a.sqrt())
         }
     }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        if a.is_negative() {
+            Err(CalcError::domain_error("Square root of negative number is undefined"))
+        } else {
+            Ok(decimal::sqrt(a, precision))
+        }
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        // the root is a fraction only if both the numerator and the denominator are squares
+        if a.is_negative() {
+            return None;
+        }
+        let (numerator, denominator) = (a.numer().sqrt(), a.denom().sqrt());
+        if &numerator * &numerator == *a.numer() && &denominator * &denominator == *a.denom() {
+            Some(Ok(BigRational::new(numerator, denominator)))
+        } else {
+            None
+        }
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.sqrt()))
+    }
 }
 
 /// sqr function
 pub struct Sqr {}
 impl Functor for Sqr {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_SQR
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
+    fn notation(&self) -> Notation {
+        Notation::Postfix
+    }
 }
 impl UnaryFunctor for Sqr {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         Ok(a * a)
     }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a.square())
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a * a))
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a * a))
+    }
 }
 /// 1/x function
 pub struct Inv {}
 impl Functor for Inv {
-    fn execute(&self) {
-        UnaryFunctor::execute(self);
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
     }
     fn id(&self) -> FunctionId {
         ID_INV
     }
     fn priority(&self) -> i32 {
-        PRIODITY_UNARY_OP
+        PRIORITY_UNARY_OP
     }
     fn arg_count(&self) -> i32 {
         1
     }
 }
 impl UnaryFunctor for Inv {
-    fn compute(&self, a: f64) -> Result<f64, &str> {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
         if a == 0.0 {
-            Err("Divide to zero")
+            Err(CalcError::division_by_zero())
         } else {
             Ok(1.0 / a)
         }
     }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::div(&BigDecimal::one(), a, precision)
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(if a.is_zero() {Err(CalcError::division_by_zero())} else {Ok(a.recip())})
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(if a.norm_sqr() == 0.0 {Err(CalcError::division_by_zero())} else {Ok(a.inv())})
+    }
+}
+/// abs function
+pub struct Abs {}
+impl Functor for Abs {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ABS
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Abs {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.abs())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a.abs())
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a.abs()))
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(Complex64::from(a.norm())))
+    }
 }
 
+/// negate function
+pub struct Neg {}
+impl Functor for Neg {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_NEG
+    }
+    fn priority(&self) -> i32 {
+        // same as power so that ±3^2 is computed as -(3^2)
+        PRIORITY_POWER
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Neg {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(-a)
+    }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(-a)
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(-a))
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(-a))
+    }
+}
 
-type FunctionCreator = fn(&String) -> Box<dyn Functor>;
-
-pub struct FunctionLib {
-    function_creator_map: HashMap<String, FunctionCreator>,
+/// ln function
+pub struct Ln {}
+impl Functor for Ln {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_LN
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Ln {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a <= 0.0 {
+            Err(CalcError::domain_error("Logarithm of non-positive number is undefined"))
+        } else {
+            Ok(a.ln())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::ln(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.ln()))
+    }
 }
 
-impl FunctionLib {
-    pub fn new() -> Self {
-        let mut function_creator_map: HashMap<String, FunctionCreator> = HashMap::new();
-        function_creator_map.insert("+".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Add{}) });
-        function_creator_map.insert("-".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Sub{}) });
-        function_creator_map.insert("*".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Mul{}) });
-        function_creator_map.insert("/".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Div{}) });
-        function_creator_map.insert("sin".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Sin{}) });
-        function_creator_map.insert("cos".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Cos{}) });
-        function_creator_map.insert("tan".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Tan{}) });
-        function_creator_map.insert("√".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Sqrt{}) });
-        function_creator_map.insert("²".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Sqr{}) });
-        function_creator_map.insert("⅟".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(Inv{}) });
-        function_creator_map.insert("(".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(OpenBracket{}) });
-        function_creator_map.insert(")".to_string(), |_: &String| -> Box<dyn Functor> { Box::new(CloseBracket{}) });
-        Self {
-            function_creator_map
-        }        
+
+/// asin function, the result is an angle in the angle mode
+pub struct Asin {}
+impl Functor for Asin {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        InverseAngleFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ASIN
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
     }
+}
+impl InverseAngleFunctor for Asin {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if !(-1.0..=1.0).contains(&a) {
+            Err(CalcError::domain_error("Arcsine is only defined for numbers from -1 to 1"))
+        } else {
+            Ok(a.asin())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        if a.abs() > BigDecimal::one() {
+            Err(CalcError::domain_error("Arcsine is only defined for numbers from -1 to 1"))
+        } else {
+            Ok(decimal::asin(a, precision))
+        }
+    }
+    fn compute_complex(&self, a: Complex64) -> Complex64 {
+        a.asin()
+    }
+}
 
-    pub fn get_functor(&self, name: &String) -> Option<Box<dyn Functor>> {
-        self.function_creator_map.get(name).map(|fn_creator| {
-            fn_creator(name)
-        })
+/// acos function, the result is an angle in the angle mode
+pub struct Acos {}
+impl Functor for Acos {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        InverseAngleFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ACOS
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl InverseAngleFunctor for Acos {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if !(-1.0..=1.0).contains(&a) {
+            Err(CalcError::domain_error("Arccosine is only defined for numbers from -1 to 1"))
+        } else {
+            Ok(a.acos())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        if a.abs() > BigDecimal::one() {
+            Err(CalcError::domain_error("Arccosine is only defined for numbers from -1 to 1"))
+        } else {
+            Ok(decimal::acos(a, precision))
+        }
+    }
+    fn compute_complex(&self, a: Complex64) -> Complex64 {
+        a.acos()
     }
 }
 
-lazy_static! {
-    pub static ref FUNCTION_LIB: FunctionLib = FunctionLib::new();
-}
\ No newline at end of file
+/// atan function, the result is an angle in the angle mode
+pub struct Atan {}
+impl Functor for Atan {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        InverseAngleFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ATAN
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl InverseAngleFunctor for Atan {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.atan())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(decimal::atan(a, precision))
+    }
+    fn compute_complex(&self, a: Complex64) -> Complex64 {
+        a.atan()
+    }
+}
+
+/// atan2 function, angle of the point (x, y) written as atan2(y, x), the result is an angle in the angle mode
+pub struct Atan2 {}
+impl Functor for Atan2 {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, 2)?;
+        let angle_mode = context.angle_mode;
+        compute_value(context.number_mode, &args,
+            |args| {
+                let (y, x) = (args[0], args[1]);
+                if x == 0.0 && y == 0.0 {
+                    return Err(CalcError::domain_error("Angle of the origin is undefined"));
+                }
+                Ok(angle_mode.from_radians(y.atan2(x)))
+            },
+            |args, precision| {
+                let (y, x) = (&args[0], &args[1]);
+                if x.is_zero() && y.is_zero() {
+                    return Err(CalcError::domain_error("Angle of the origin is undefined"));
+                }
+                let work = precision + decimal::GUARD_DIGITS;
+                // the angle is from -π to π, atan gives an angle in the right half plane
+                // and the angle in the left half plane is half a turn away from it
+                let radians = if x.is_zero() {
+                    if y.is_positive() { decimal::pi(work).half() } else { -decimal::pi(work).half() }
+                } else {
+                    let angle = decimal::atan(&decimal::div(y, x, work)?, work);
+                    if x.is_positive() { angle }
+                    else if y.is_negative() { angle - decimal::pi(work) }
+                    else { angle + decimal::pi(work) }
+                };
+                Ok(angle_mode.from_radians_decimal(&radians, work))
+            })
+    }
+    fn id(&self) -> FunctionId {
+        ID_ATAN2
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+
+/// sinh function
+pub struct Sinh {}
+impl Functor for Sinh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_SINH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Sinh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.sinh())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::sinh(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.sinh()))
+    }
+}
+
+/// cosh function
+pub struct Cosh {}
+impl Functor for Cosh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_COSH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Cosh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.cosh())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::cosh(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.cosh()))
+    }
+}
+
+/// tanh function
+pub struct Tanh {}
+impl Functor for Tanh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_TANH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Tanh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.tanh())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::tanh(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.tanh()))
+    }
+}
+
+/// asinh function
+pub struct Asinh {}
+impl Functor for Asinh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ASINH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Asinh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.asinh())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::asinh(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.asinh()))
+    }
+}
+
+/// acosh function
+pub struct Acosh {}
+impl Functor for Acosh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ACOSH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Acosh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a < 1.0 {
+            Err(CalcError::domain_error("Inverse hyperbolic cosine is only defined for numbers from 1"))
+        } else {
+            Ok(a.acosh())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        if *a < BigDecimal::one() {
+            Err(CalcError::domain_error("Inverse hyperbolic cosine is only defined for numbers from 1"))
+        } else {
+            decimal::acosh(a, precision)
+        }
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.acosh()))
+    }
+}
+
+/// atanh function
+pub struct Atanh {}
+impl Functor for Atanh {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ATANH
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Atanh {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a <= -1.0 || a >= 1.0 {
+            Err(CalcError::domain_error("Inverse hyperbolic tangent is only defined for numbers between -1 and 1"))
+        } else {
+            Ok(a.atanh())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        if a.abs() >= BigDecimal::one() {
+            Err(CalcError::domain_error("Inverse hyperbolic tangent is only defined for numbers between -1 and 1"))
+        } else {
+            decimal::atanh(a, precision)
+        }
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.atanh()))
+    }
+}
+
+/// common logarithm
+pub struct Log10 {}
+impl Functor for Log10 {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_LOG10
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Log10 {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a <= 0.0 {
+            Err(CalcError::domain_error("Logarithm of non-positive number is undefined"))
+        } else {
+            Ok(a.log10())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::log(a, &BigDecimal::from(10), precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.log10()))
+    }
+}
+
+/// binary logarithm
+pub struct Log2 {}
+impl Functor for Log2 {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_LOG2
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Log2 {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a <= 0.0 {
+            Err(CalcError::domain_error("Logarithm of non-positive number is undefined"))
+        } else {
+            Ok(a.log2())
+        }
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::log(a, &BigDecimal::from(2), precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.log2()))
+    }
+}
+
+/// logarithm to the given base such as log(8, 2), the base is 10 if it is not given
+pub struct Log {}
+impl Functor for Log {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_LOG
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+impl NaryFunctor for Log {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        let a = args[0];
+        let base = args.get(1).copied().unwrap_or(10.0);
+        if a <= 0.0 {
+            return Err(CalcError::domain_error("Logarithm of non-positive number is undefined"));
+        }
+        if base <= 0.0 || base == 1.0 {
+            return Err(CalcError::domain_error("Base of logarithm must be positive and not 1"));
+        }
+        Ok(a.ln() / base.ln())
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], precision: u64) -> Result<BigDecimal, CalcError> {
+        let a = &args[0];
+        let base = args.get(1).cloned().unwrap_or_else(|| BigDecimal::from(10));
+        if !a.is_positive() {
+            return Err(CalcError::domain_error("Logarithm of non-positive number is undefined"));
+        }
+        if !base.is_positive() || base.is_one() {
+            return Err(CalcError::domain_error("Base of logarithm must be positive and not 1"));
+        }
+        decimal::log(a, &base, precision)
+    }
+    fn compute_complex(&self, args: &[Complex64]) -> Option<Result<Complex64, CalcError>> {
+        let base = args.get(1).copied().unwrap_or(Complex64::from(10.0));
+        if base.norm_sqr() == 0.0 || base == Complex64::from(1.0) {
+            return Some(Err(CalcError::domain_error("Base of logarithm must not be 0 or 1")));
+        }
+        Some(Ok(args[0].ln() / base.ln()))
+    }
+}
+
+/// exponential function
+pub struct Exp {}
+impl Functor for Exp {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_EXP
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Exp {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a.exp())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::exp(a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.exp()))
+    }
+}
+
+/// power of ten
+pub struct Exp10 {}
+impl Functor for Exp10 {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_EXP10
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Exp10 {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(10f64.powf(a))
+    }
+    fn compute_decimal(&self, a: &BigDecimal, precision: u64) -> Result<BigDecimal, CalcError> {
+        decimal::pow(&BigDecimal::from(10), a, precision)
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a.expf(10.0)))
+    }
+}
+
+/// factorial function, written after its operand such as 5!
+pub struct Factorial {}
+impl Functor for Factorial {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_FACTORIAL
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn notation(&self) -> Notation {
+        Notation::Postfix
+    }
+}
+impl UnaryFunctor for Factorial {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a < 0.0 || a.fract() != 0.0 {
+            return Err(CalcError::domain_error("Factorial is only defined for non-negative integers, use gamma for other numbers"));
+        }
+        // 171! is out of range of f64
+        if a > 170.0 {
+            return Err(CalcError::overflow());
+        }
+        Ok((2..=a as u32).fold(1.0, |product, i| product * i as f64))
+    }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        match decimal::to_natural(a)? {
+            Some(n) => decimal::factorial(&n),
+            _ => Err(CalcError::domain_error("Factorial is only defined for non-negative integers, use gamma for other numbers")),
+        }
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        compute_rational_by_decimal(std::slice::from_ref(a), |args| self.compute_decimal(&args[0], 0))
+    }
+}
+
+/// gamma function, gamma(n) is (n - 1)! for positive integers
+pub struct Gamma {}
+impl Functor for Gamma {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_GAMMA
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+}
+impl UnaryFunctor for Gamma {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        if a <= 0.0 && a.fract() == 0.0 {
+            return Err(CalcError::domain_error("Gamma function is undefined for zero and negative integers"));
+        }
+        if a.fract() == 0.0 {
+            // exact result for integers
+            return Factorial{}.compute(a - 1.0);
+        }
+        Ok(gamma(a))
+    }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        match decimal::to_natural(a)? {
+            Some(n) if n.is_zero() => Err(CalcError::domain_error("Gamma function is undefined for zero and negative integers")),
+            // exact result for integers
+            Some(n) => decimal::factorial(&(n - 1)),
+            // the Lanczos approximation has no more digits than f64, it rejects negative integers
+            None => compute_decimal_by_f64(std::slice::from_ref(a), |args| self.compute(args[0])),
+        }
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        compute_rational_by_decimal(std::slice::from_ref(a), |args| self.compute_decimal(&args[0], 0))
+    }
+}
+
+/// Lanczos approximation of the gamma function, it is accurate to about 15 significant digits
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    // split the power so that it does not overflow before the division
+    let power = t.powf((x + 0.5) / 2.0);
+    (2.0 * std::f64::consts::PI).sqrt() * power * (power * (-t).exp()) * sum
+}
+
+/// check the arguments of nPr and nCr
+fn check_combination(n: f64, r: f64) -> Result<(), CalcError> {
+    if n < 0.0 || r < 0.0 || n.fract() != 0.0 || r.fract() != 0.0 {
+        return Err(CalcError::domain_error("Arguments of permutations and combinations must be non-negative integers"));
+    }
+    if r > n {
+        return Err(CalcError::domain_error("Cannot choose more items than there are"));
+    }
+    Ok(())
+}
+
+/// check the arguments of nPr and nCr given as decimals, they are returned as integers
+fn check_combination_decimal(n: &BigDecimal, r: &BigDecimal) -> Result<(BigInt, BigInt), CalcError> {
+    match (decimal::to_natural(n)?, decimal::to_natural(r)?) {
+        (Some(n), Some(r)) => {
+            if r > n {
+                return Err(CalcError::domain_error("Cannot choose more items than there are"));
+            }
+            Ok((n, r))
+        }
+        _ => Err(CalcError::domain_error("Arguments of permutations and combinations must be non-negative integers")),
+    }
+}
+
+/// number of permutations of r items chosen from n items, written as 5 nPr 2 or nPr(5, 2)
+pub struct Permutation {}
+impl Functor for Permutation {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_PERMUTATION
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_POWER
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+}
+impl BinaryFunctor for Permutation {
+    fn compute(&self, n: f64, r: f64) -> Result<f64, CalcError> {
+        check_combination(n, r)?;
+        let mut product: f64 = 1.0;
+        let mut i = 0.0;
+        // stop as soon as the product is out of range so that huge numbers do not take forever
+        while i < r && product.is_finite() {
+            product *= n - i;
+            i += 1.0;
+        }
+        Ok(product)
+    }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        let (n, r) = check_combination_decimal(a, b)?;
+        decimal::permutation(&n, &r)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        compute_rational_by_decimal(&[a.clone(), b.clone()], |args| self.compute_decimal(&args[0], &args[1], 0))
+    }
+}
+
+/// number of combinations of r items chosen from n items, written as 5 nCr 2 or nCr(5, 2)
+pub struct Combination {}
+impl Functor for Combination {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        BinaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_COMBINATION
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_POWER
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+}
+impl BinaryFunctor for Combination {
+    fn compute(&self, n: f64, r: f64) -> Result<f64, CalcError> {
+        check_combination(n, r)?;
+        // nCr is the same as nC(n - r), choose the smaller one to have less steps
+        let r = r.min(n - r);
+        let mut product: f64 = 1.0;
+        let mut i = 1.0;
+        while i <= r && product.is_finite() {
+            // the product is always an integer after each step
+            product = product * (n - r + i) / i;
+            i += 1.0;
+        }
+        Ok(product.round())
+    }
+    fn compute_decimal(&self, a: &BigDecimal, b: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        let (n, r) = check_combination_decimal(a, b)?;
+        decimal::combination(&n, &r)
+    }
+    fn compute_rational(&self, a: &BigRational, b: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        compute_rational_by_decimal(&[a.clone(), b.clone()], |args| self.compute_decimal(&args[0], &args[1], 0))
+    }
+}
+
+/// percent function, written after its operand such as 10%.
+/// A percentage added to or subtracted from a number is a percentage of that number such as 200 + 10%,
+/// the evaluator takes care of that case
+pub struct Percent {}
+impl Functor for Percent {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        UnaryFunctor::execute(self, context)
+    }
+    fn id(&self) -> FunctionId {
+        ID_PERCENT
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_UNARY_OP
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn notation(&self) -> Notation {
+        Notation::Postfix
+    }
+}
+impl UnaryFunctor for Percent {
+    fn compute(&self, a: f64) -> Result<f64, CalcError> {
+        Ok(a / 100.0)
+    }
+    fn compute_decimal(&self, a: &BigDecimal, _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(a * BigDecimal::new(1.into(), 2))
+    }
+    fn compute_rational(&self, a: &BigRational) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(a / BigInt::from(100)))
+    }
+    fn compute_complex(&self, a: Complex64) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(a / 100.0))
+    }
+}
+
+/// whether the functor adds or subtracts, a percentage following it is taken of the number before it
+pub fn is_additive(functor: &dyn Functor) -> bool {
+    functor.id() == ID_ADD || functor.id() == ID_SUB
+}
+
+/// the given percentage of a number, the percentage is already divided by 100
+pub fn percent_of(mode: NumberMode, percentage: Value, base: Value) -> Result<Value, CalcError> {
+    let args = [percentage, base];
+    check_numbers(&args)?;
+    if let Some(result) = compute_exact(mode, &args, |args| Some(Ok(&args[0] * &args[1]))) {
+        return result;
+    }
+    if mode == NumberMode::Complex {
+        return compute_complex_value(&args, |args| Ok(args[0] * args[1]), |args| Some(Ok(args[0] * args[1])));
+    }
+    compute_value(mode, &args,
+        |args| Ok(args[0] * args[1]),
+        |args, _| Ok(&args[0] * &args[1]))
+}
+
+/// max function
+pub struct Max {}
+impl Functor for Max {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_MAX
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        None
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Max {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(args.iter().max().unwrap().clone())
+    }
+    fn compute_rational(&self, args: &[BigRational]) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(args.iter().max().unwrap().clone()))
+    }
+}
+
+/// min function
+pub struct Min {}
+impl Functor for Min {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_MIN
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        None
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Min {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        Ok(args.iter().copied().fold(f64::INFINITY, f64::min))
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(args.iter().min().unwrap().clone())
+    }
+    fn compute_rational(&self, args: &[BigRational]) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(args.iter().min().unwrap().clone()))
+    }
+}
+
+/// sum function
+pub struct Sum {}
+impl Functor for Sum {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_SUM
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        None
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Sum {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        Ok(args.iter().sum())
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], _precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(args.iter().fold(BigDecimal::zero(), |sum, a| sum + a))
+    }
+    fn compute_rational(&self, args: &[BigRational]) -> Option<Result<BigRational, CalcError>> {
+        Some(Ok(args.iter().fold(BigRational::zero(), |sum, a| sum + a)))
+    }
+    fn compute_complex(&self, args: &[Complex64]) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(args.iter().sum()))
+    }
+}
+
+/// average function
+pub struct Avg {}
+impl Functor for Avg {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_AVG
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        None
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Avg {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        Ok(args.iter().sum::<f64>() / args.len() as f64)
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], precision: u64) -> Result<BigDecimal, CalcError> {
+        let sum = args.iter().fold(BigDecimal::zero(), |sum, a| sum + a);
+        decimal::div(&sum, &BigDecimal::from(args.len() as u64), precision)
+    }
+    fn compute_rational(&self, args: &[BigRational]) -> Option<Result<BigRational, CalcError>> {
+        let sum = args.iter().fold(BigRational::zero(), |sum, a| sum + a);
+        Some(Ok(sum / BigInt::from(args.len())))
+    }
+    fn compute_complex(&self, args: &[Complex64]) -> Option<Result<Complex64, CalcError>> {
+        Some(Ok(args.iter().sum::<Complex64>() / args.len() as f64))
+    }
+}
+
+/// length of the hypotenuse, or of a vector with more than two components
+pub struct Hypot {}
+impl Functor for Hypot {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 2)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_HYPOT
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        2
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        None
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Hypot {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        Ok(args.iter().fold(0.0, |length, a| length.hypot(*a)))
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], precision: u64) -> Result<BigDecimal, CalcError> {
+        Ok(decimal::sqrt(&args.iter().fold(BigDecimal::zero(), |sum, a| sum + a.square()), precision))
+    }
+}
+
+/// round to the nearest integer, or to the given number of decimal places such as round(x, 2)
+pub struct Round {}
+impl Functor for Round {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, 1)
+    }
+    fn execute_args(&self, context: &mut Context, count: usize) -> Result<Value, CalcError> {
+        NaryFunctor::execute(self, context, count)
+    }
+    fn id(&self) -> FunctionId {
+        ID_ROUND
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn arg_count(&self) -> i32 {
+        1
+    }
+    fn max_arg_count(&self) -> Option<usize> {
+        Some(2)
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+impl NaryFunctor for Round {
+    fn compute(&self, args: &[f64]) -> Result<f64, CalcError> {
+        let places = args.get(1).copied().unwrap_or(0.0);
+        if places.fract() != 0.0 {
+            return Err(CalcError::domain_error("Number of decimal places must be an integer"));
+        }
+        // scale by a power of ten which is an exact integer so that no error is added by the scaling
+        let scale = 10f64.powi(places.abs().min(400.0) as i32);
+        if scale.is_infinite() {
+            // every finite number is rounded to zero at such a place before the decimal point,
+            // and it has no digit at such a place after it
+            return Ok(if places < 0.0 { 0f64.copysign(args[0]) } else { args[0] });
+        }
+        if places < 0.0 {
+            return Ok((args[0] / scale).round() * scale);
+        }
+        let scaled = args[0] * scale;
+        if scaled.is_infinite() {
+            // the number has no digit at such a decimal place
+            return Ok(args[0]);
+        }
+        Ok(scaled.round() / scale)
+    }
+    fn compute_decimal(&self, args: &[BigDecimal], _precision: u64) -> Result<BigDecimal, CalcError> {
+        let places = match args.get(1) {
+            Some(places) => decimal::to_integer(places)
+                .ok_or_else(|| CalcError::domain_error("Number of decimal places must be an integer"))?,
+            None => 0.into(),
+        };
+        // the number has no digit beyond its own decimal places
+        match places.to_i64() {
+            Some(places) if places < args[0].fractional_digit_count() => Ok(args[0].with_scale_round(places, RoundingMode::HalfUp)),
+            _ if places.is_negative() => Ok(BigDecimal::zero()),
+            _ => Ok(args[0].clone()),
+        }
+    }
+}
+
+/// Whether operators with the same priority are grouped from left to right or from right to left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// 1 - 2 - 3 is (1 - 2) - 3
+    Left,
+    /// 2 ^ 3 ^ 2 is 2 ^ (3 ^ 2)
+    Right,
+}
+
+type FunctionImpl = Arc<dyn Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync>;
+
+/// A functor registered at runtime with a closure computing its result from the arguments,
+/// it computes with f64 in every number mode
+#[derive(Clone)]
+pub struct RegisteredFunction {
+    id: FunctionId,
+    arity: i32,
+    priority: i32,
+    associativity: Associativity,
+    function: FunctionImpl,
+}
+impl Functor for RegisteredFunction {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        let args = pop_numbers(&mut context.execution_stack, self.arity as usize)?;
+        compute_value(context.number_mode, &args,
+            |args| (self.function)(args),
+            |args, _| compute_decimal_by_f64(args, |args| (self.function)(args)))
+    }
+    fn id(&self) -> FunctionId {
+        self.id
+    }
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+    fn arg_count(&self) -> i32 {
+        self.arity
+    }
+    fn right_associative(&self) -> bool {
+        self.associativity == Associativity::Right
+    }
+}
+
+type FunctionCreator = Arc<dyn Fn(&String) -> Box<dyn Functor> + Send + Sync>;
+type ConstantCreator = Arc<dyn Fn(NumberMode) -> Option<Value> + Send + Sync>;
+
+/// Registry of all functors and constants by their names.
+/// Each evaluator owns a copy of the built-in library, functions can be added, replaced or removed
+/// and libraries can be layered on top of each other under a namespace such as stats.mean
+#[derive(Clone)]
+pub struct FunctionLib {
+    function_creator_map: HashMap<String, FunctionCreator>,
+    constant_map: HashMap<String, ConstantCreator>,
+    next_function_id: FunctionId,
+}
+
+impl Default for FunctionLib {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionLib {
+    /// a library with all built-in functors and constants
+    pub fn new() -> Self {
+        let mut lib = Self::empty();
+        lib.register_functor("+", |_: &String| -> Box<dyn Functor> { Box::new(Add{}) });
+        lib.register_functor("-", |_: &String| -> Box<dyn Functor> { Box::new(Sub{}) });
+        lib.register_functor("*", |_: &String| -> Box<dyn Functor> { Box::new(Mul{}) });
+        lib.register_functor("/", |_: &String| -> Box<dyn Functor> { Box::new(Div{}) });
+        lib.register_functor("%", |_: &String| -> Box<dyn Functor> { Box::new(Percent{}) });
+        lib.register_functor("mod", |_: &String| -> Box<dyn Functor> { Box::new(Mod{}) });
+        lib.register_functor("^", |_: &String| -> Box<dyn Functor> { Box::new(Pow{}) });
+        lib.register_functor("sin", |_: &String| -> Box<dyn Functor> { Box::new(Sin{}) });
+        lib.register_functor("cos", |_: &String| -> Box<dyn Functor> { Box::new(Cos{}) });
+        lib.register_functor("tan", |_: &String| -> Box<dyn Functor> { Box::new(Tan{}) });
+        lib.register_functor("√", |_: &String| -> Box<dyn Functor> { Box::new(Sqrt{}) });
+        lib.register_functor("²", |_: &String| -> Box<dyn Functor> { Box::new(Sqr{}) });
+        lib.register_functor("⅟", |_: &String| -> Box<dyn Functor> { Box::new(Inv{}) });
+        lib.register_functor("abs", |_: &String| -> Box<dyn Functor> { Box::new(Abs{}) });
+        lib.register_functor("±", |_: &String| -> Box<dyn Functor> { Box::new(Neg{}) });
+        lib.register_functor("ln", |_: &String| -> Box<dyn Functor> { Box::new(Ln{}) });
+        lib.register_functor("log10", |_: &String| -> Box<dyn Functor> { Box::new(Log10{}) });
+        lib.register_functor("log2", |_: &String| -> Box<dyn Functor> { Box::new(Log2{}) });
+        lib.register_functor("log", |_: &String| -> Box<dyn Functor> { Box::new(Log{}) });
+        lib.register_functor("exp", |_: &String| -> Box<dyn Functor> { Box::new(Exp{}) });
+        lib.register_functor("exp10", |_: &String| -> Box<dyn Functor> { Box::new(Exp10{}) });
+        lib.register_functor("!", |_: &String| -> Box<dyn Functor> { Box::new(Factorial{}) });
+        lib.register_functor("gamma", |_: &String| -> Box<dyn Functor> { Box::new(Gamma{}) });
+        lib.register_functor("nPr", |_: &String| -> Box<dyn Functor> { Box::new(Permutation{}) });
+        lib.register_functor("nCr", |_: &String| -> Box<dyn Functor> { Box::new(Combination{}) });
+        lib.register_functor("asin", |_: &String| -> Box<dyn Functor> { Box::new(Asin{}) });
+        lib.register_functor("acos", |_: &String| -> Box<dyn Functor> { Box::new(Acos{}) });
+        lib.register_functor("atan", |_: &String| -> Box<dyn Functor> { Box::new(Atan{}) });
+        lib.register_functor("atan2", |_: &String| -> Box<dyn Functor> { Box::new(Atan2{}) });
+        lib.register_functor("sinh", |_: &String| -> Box<dyn Functor> { Box::new(Sinh{}) });
+        lib.register_functor("cosh", |_: &String| -> Box<dyn Functor> { Box::new(Cosh{}) });
+        lib.register_functor("tanh", |_: &String| -> Box<dyn Functor> { Box::new(Tanh{}) });
+        lib.register_functor("asinh", |_: &String| -> Box<dyn Functor> { Box::new(Asinh{}) });
+        lib.register_functor("acosh", |_: &String| -> Box<dyn Functor> { Box::new(Acosh{}) });
+        lib.register_functor("atanh", |_: &String| -> Box<dyn Functor> { Box::new(Atanh{}) });
+        lib.register_functor("(", |_: &String| -> Box<dyn Functor> { Box::new(OpenBracket{}) });
+        lib.register_functor(")", |_: &String| -> Box<dyn Functor> { Box::new(CloseBracket{}) });
+        lib.register_functor(",", |_: &String| -> Box<dyn Functor> { Box::new(Comma{}) });
+        lib.register_functor("max", |_: &String| -> Box<dyn Functor> { Box::new(Max{}) });
+        lib.register_functor("min", |_: &String| -> Box<dyn Functor> { Box::new(Min{}) });
+        lib.register_functor("sum", |_: &String| -> Box<dyn Functor> { Box::new(Sum{}) });
+        lib.register_functor("avg", |_: &String| -> Box<dyn Functor> { Box::new(Avg{}) });
+        lib.register_functor("hypot", |_: &String| -> Box<dyn Functor> { Box::new(Hypot{}) });
+        lib.register_functor("round", |_: &String| -> Box<dyn Functor> { Box::new(Round{}) });
+        lib.register_constant_creator("π", |mode| match mode {
+            NumberMode::Float | NumberMode::Rational => Some(Value::Number(std::f64::consts::PI)),
+            NumberMode::Decimal(precision) => Some(Value::Decimal(decimal::pi(precision))),
+            NumberMode::Complex => Some(Value::Complex(Complex64::from(std::f64::consts::PI))),
+            // irrational constants are not integers
+            NumberMode::Integer(_) => None,
+        });
+        lib.register_constant_creator("e", |mode| match mode {
+            NumberMode::Float | NumberMode::Rational => Some(Value::Number(std::f64::consts::E)),
+            // e^1 never overflows
+            NumberMode::Decimal(precision) => Some(Value::Decimal(decimal::exp(&BigDecimal::one(), precision).unwrap())),
+            NumberMode::Complex => Some(Value::Complex(Complex64::from(std::f64::consts::E))),
+            NumberMode::Integer(_) => None,
+        });
+        // the imaginary unit is only a number in complex mode
+        lib.register_constant_creator("i", |mode| (mode == NumberMode::Complex).then(|| Value::Complex(Complex64::i())));
+        lib
+    }
+
+    /// a library without any functor or constant, used to build a domain specific library
+    pub fn empty() -> Self {
+        Self {
+            function_creator_map: HashMap::new(),
+            constant_map: HashMap::new(),
+            next_function_id: ID_REGISTERED_FUNCTION_BASE,
+        }
+    }
+
+    /// register a function computed by the given closure, an existing function with the same name is replaced.
+    /// One argument functions are written before their argument, two argument functions between their arguments
+    /// and other functions are called with a bracketed argument list
+    pub fn register<F>(&mut self, name: &str, arity: i32, priority: i32, associativity: Associativity, function: F) -> FunctionId
+    where
+        F: Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync + 'static,
+    {
+        let id = self.next_function_id;
+        self.next_function_id += 1;
+        let functor = RegisteredFunction {
+            id,
+            arity,
+            priority,
+            associativity,
+            function: Arc::new(function),
+        };
+        self.register_functor(name, move |_: &String| -> Box<dyn Functor> { Box::new(functor.clone()) });
+        id
+    }
+
+    /// register a closure creating the functor for the given name, an existing function with the same name is replaced
+    pub fn register_functor<F>(&mut self, name: &str, creator: F)
+    where
+        F: Fn(&String) -> Box<dyn Functor> + Send + Sync + 'static,
+    {
+        self.function_creator_map.insert(name.to_string(), Arc::new(creator));
+    }
+
+    /// register a constant which has the same value in every number mode
+    pub fn register_constant(&mut self, name: &str, value: f64) {
+        self.register_constant_creator(name, move |mode| Some(Value::from_f64(value, mode).unwrap_or(Value::Number(value))));
+    }
+
+    /// register a closure computing the constant in the given number mode, such as π to the precision of a decimal mode,
+    /// it returns none if the constant is not a number in the mode such as i which is only a number in complex mode
+    pub fn register_constant_creator<F>(&mut self, name: &str, creator: F)
+    where
+        F: Fn(NumberMode) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.constant_map.insert(name.to_string(), Arc::new(creator));
+    }
+
+    /// remove a function, return false if there is no function with the given name
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.function_creator_map.remove(name).is_some()
+    }
+
+    /// remove a constant, return false if there is no constant with the given name
+    pub fn unregister_constant(&mut self, name: &str) -> bool {
+        self.constant_map.remove(name).is_some()
+    }
+
+    /// add all functions and constants of another library, their names are prefixed by the namespace and a dot
+    /// such as stats.mean, they are added with their own names if the namespace is empty
+    pub fn import(&mut self, namespace: &str, lib: &FunctionLib) {
+        let qualify = |name: &String| if namespace.is_empty() { name.clone() } else { format!("{}.{}", namespace, name) };
+        for (name, creator) in &lib.function_creator_map {
+            self.function_creator_map.insert(qualify(name), Arc::clone(creator));
+        }
+        for (name, creator) in &lib.constant_map {
+            self.constant_map.insert(qualify(name), Arc::clone(creator));
+        }
+    }
+
+    /// names of all registered functions, sorted
+    pub fn function_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.function_creator_map.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// create the functor registered under the given name
+    pub fn get_functor(&self, name: &String) -> Option<Box<dyn Functor>> {
+        self.function_creator_map.get(name).map(|fn_creator| {
+            fn_creator(name)
+        })
+    }
+
+    /// whether a constant is registered under the given name
+    pub fn has_constant(&self, name: &String) -> bool {
+        self.constant_map.contains_key(name)
+    }
+
+    /// value of the constant in the given number mode
+    pub fn get_constant(&self, name: &String, mode: NumberMode) -> Option<Value> {
+        self.constant_map.get(name).and_then(|creator| creator(mode))
+    }
+}
+
+lazy_static! {
+    /// the built-in library, each evaluator starts with a copy of it
+    pub static ref FUNCTION_LIB: FunctionLib = FunctionLib::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::context::AngleMode;
+    use crate::calc::evaluator::Evaluator;
+    use crate::calc::value::IntegerMode;
+    use std::str::FromStr;
+
+    #[test]
+    fn modulo_keeps_the_sign_of_the_dividend() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("7 mod 3"), Ok(Value::Number(1.0)));
+        assert_eq!(evaluator.evaluate_str("-7 mod 3"), Ok(Value::Number(-1.0)));
+        assert_eq!(evaluator.evaluate_str("7.5 mod 2"), Ok(Value::Number(1.5)));
+        assert_eq!(evaluator.evaluate_str("7 mod 0"),
+            Err(CalcError::DivisionByZero { token: "mod".to_string(), span: Span::new(2, 5) }));
+        evaluator.set_number_mode(NumberMode::Decimal(32));
+        assert_eq!(evaluator.evaluate_str("-7.5 mod 2"), Ok(Value::Decimal(BigDecimal::from_str("-1.5").unwrap())));
+        assert_eq!(evaluator.evaluate_str("1e300000000 mod 7"), Ok(Value::Decimal(BigDecimal::from(1))));
+    }
+
+    #[test]
+    fn abs_accepts_only_numbers() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("abs(-2.5)"), Ok(Value::Number(2.5)));
+        assert_eq!(evaluator.evaluate_str("abs()"),
+            Err(CalcError::ArgumentCount { token: "abs".to_string(), span: Span::new(0, 3), expected: 1, found: 0 }));
+        evaluator.set_variable("s", Value::String("a".to_string()));
+        assert_eq!(evaluator.evaluate_str("abs s"), Err(CalcError::TypeError {
+            token: "abs".to_string(), span: Span::new(0, 3), expected: ValueKind::Number, found: ValueKind::String }));
+    }
+
+    #[test]
+    fn ln_of_non_positive_numbers() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("ln e"), Ok(Value::Number(1.0)));
+        let undefined = |start| Err(CalcError::DomainError {
+            token: "ln".to_string(), span: Span::new(start, start + 2), message: "Logarithm of non-positive number is undefined".to_string() });
+        assert_eq!(evaluator.evaluate_str("ln 0"), undefined(0));
+        assert_eq!(evaluator.evaluate_str("1 + ln(-1)"), undefined(4));
+        // a negative number has a logarithm in complex mode
+        evaluator.set_number_mode(NumberMode::Complex);
+        assert_eq!(evaluator.evaluate_str("ln(-1)"), Ok(Value::Complex(Complex64::new(0.0, std::f64::consts::PI))));
+    }
+
+    #[test]
+    fn variadic_functions_take_any_number_of_arguments() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("max(3, 7, 2)"), Ok(Value::Number(7.0)));
+        assert_eq!(evaluator.evaluate_str("min(4, 1+1)"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluator.evaluate_str("sum(1, 2, 3, 4)"), Ok(Value::Number(10.0)));
+        assert_eq!(evaluator.evaluate_str("avg(2, 4) * max(1)"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluator.evaluate_str("max()"),
+            Err(CalcError::ArgumentCount { token: "max".to_string(), span: Span::new(0, 3), expected: 1, found: 0 }));
+        assert_eq!(evaluator.evaluate_str("1, 2"), Err(CalcError::UnexpectedToken { token: ",".to_string(), span: Span::new(1, 2) }));
+    }
+
+    #[test]
+    fn fixed_functions_take_their_number_of_arguments() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("hypot(3, 4)"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluator.evaluate_str("hypot(3)"),
+            Err(CalcError::ArgumentCount { token: "hypot".to_string(), span: Span::new(0, 5), expected: 2, found: 1 }));
+        assert_eq!(evaluator.evaluate_str("round(1, 2, 3)"),
+            Err(CalcError::ArgumentCount { token: "round".to_string(), span: Span::new(0, 5), expected: 2, found: 3 }));
+    }
+
+    #[test]
+    fn round_to_decimal_places() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("round(1.23456, 2)"), Ok(Value::Number(1.23)));
+        assert_eq!(evaluator.evaluate_str("round(2.5)"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluator.evaluate_str("round(1234, ±2)"), Ok(Value::Number(1200.0)));
+        // the scale of too many places overflows, the number is rounded to zero or kept
+        assert_eq!(evaluator.evaluate_str("round(1, -400)"), Ok(Value::Number(0.0)));
+        assert!(evaluator.evaluate_str("round(-1e300, -400)").is_ok_and(|v| v == Value::Number(0.0) && v.to_f64().is_sign_negative()));
+        assert_eq!(evaluator.evaluate_str("round(0, 400)"), Ok(Value::Number(0.0)));
+        assert_eq!(evaluator.evaluate_str("round(1.5, 1000)"), Ok(Value::Number(1.5)));
+    }
+
+    #[test]
+    fn trigonometric_functions_take_radians_by_default() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("cos π"), Ok(Value::Number(-1.0)));
+        assert_eq!(evaluator.evaluate_str("sin 0 + tan 0"), Ok(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn trigonometric_functions_in_degrees() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_angle_mode(AngleMode::Deg);
+        assert_eq!(evaluator.evaluate_str("sin 180 + cos 360"), Ok(Value::Number(1.0)));
+        assert!((evaluator.evaluate_str("sin 30").unwrap().to_f64() - 0.5).abs() < 1e-15);
+        // a right angle is exact in degrees so its tangent is undefined
+        assert_eq!(evaluator.evaluate_str("1 + tan 90"), Err(CalcError::DomainError {
+            token: "tan".to_string(), span: Span::new(4, 7), message: "Tangent of a right angle is undefined".to_string() }));
+    }
+
+    #[test]
+    fn trigonometric_functions_in_gradians() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_angle_mode(AngleMode::Grad);
+        assert_eq!(evaluator.evaluate_str("sin 100 + cos 200"), Ok(Value::Number(0.0)));
+        assert!((evaluator.evaluate_str("tan 50").unwrap().to_f64() - 1.0).abs() < 1e-15);
+        assert!(matches!(evaluator.evaluate_str("tan 100"), Err(CalcError::DomainError { .. })));
+        assert_eq!(AngleMode::Grad.next(), AngleMode::Deg);
+    }
+
+    #[test]
+    fn inverse_functions_return_angles_in_the_angle_mode() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("asin 1 * 2"), Ok(Value::Number(std::f64::consts::PI)));
+        evaluator.set_angle_mode(AngleMode::Deg);
+        assert_eq!(evaluator.evaluate_str("acos 0 + atan 1"), Ok(Value::Number(135.0)));
+        assert_eq!(evaluator.evaluate_str("atan2(1, ±1)"), Ok(Value::Number(135.0)));
+        evaluator.set_angle_mode(AngleMode::Grad);
+        assert_eq!(evaluator.evaluate_str("asin 1"), Ok(Value::Number(100.0)));
+    }
+
+    #[test]
+    fn hyperbolic_functions_are_inverted() {
+        let mut evaluator = Evaluator::new();
+        assert!((evaluator.evaluate_str("tanh(atanh 0.5)").unwrap().to_f64() - 0.5).abs() < 1e-15);
+        assert!((evaluator.evaluate_str("acosh(cosh 2) + asinh(sinh 1)").unwrap().to_f64() - 3.0).abs() < 1e-15);
+        assert_eq!(evaluator.evaluate_str("cosh 1000"), Err(CalcError::Overflow { token: "cosh".to_string(), span: Span::new(0, 4) }));
+    }
+
+    #[test]
+    fn inverse_functions_out_of_their_domain() {
+        let mut evaluator = Evaluator::new();
+        let domain_error = |token: &str, start, message: &str| Err(CalcError::DomainError {
+            token: token.to_string(), span: Span::new(start, start + token.chars().count()), message: message.to_string() });
+        assert_eq!(evaluator.evaluate_str("1 + asin(2)"), domain_error("asin", 4, "Arcsine is only defined for numbers from -1 to 1"));
+        assert_eq!(evaluator.evaluate_str("acosh 0.5"), domain_error("acosh", 0, "Inverse hyperbolic cosine is only defined for numbers from 1"));
+        assert_eq!(evaluator.evaluate_str("atanh 1"),
+            domain_error("atanh", 0, "Inverse hyperbolic tangent is only defined for numbers between -1 and 1"));
+        assert_eq!(evaluator.evaluate_str("atan2(0, 0)"), domain_error("atan2", 0, "Angle of the origin is undefined"));
+    }
+
+    #[test]
+    fn logarithms_of_any_base() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("log10 1000 + log2 8"), Ok(Value::Number(6.0)));
+        // log is decimal with one argument
+        assert_eq!(evaluator.evaluate_str("log 100"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluator.evaluate_str("log(8, 2)"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluator.evaluate_str("log(8, 1)"), Err(CalcError::DomainError {
+            token: "log".to_string(), span: Span::new(0, 3), message: "Base of logarithm must be positive and not 1".to_string() }));
+    }
+
+    #[test]
+    fn logarithms_of_non_positive_numbers() {
+        let mut evaluator = Evaluator::new();
+        let undefined = |token: &str| Err(CalcError::DomainError {
+            token: token.to_string(), span: Span::new(0, token.len()), message: "Logarithm of non-positive number is undefined".to_string() });
+        assert_eq!(evaluator.evaluate_str("log10 0"), undefined("log10"));
+        assert_eq!(evaluator.evaluate_str("log2(1 - 2)"), undefined("log2"));
+    }
+
+    #[test]
+    fn exponentials_overflow() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("exp 0 + exp10 2"), Ok(Value::Number(101.0)));
+        assert_eq!(evaluator.evaluate_str("exp 1000"), Err(CalcError::Overflow { token: "exp".to_string(), span: Span::new(0, 3) }));
+        assert_eq!(evaluator.evaluate_str("exp10 400"), Err(CalcError::Overflow { token: "exp10".to_string(), span: Span::new(0, 5) }));
+    }
+
+    #[test]
+    fn factorial_of_non_negative_integers() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("3! + 0!"), Ok(Value::Number(7.0)));
+        // factorial binds tighter than power
+        assert_eq!(evaluator.evaluate_str("2^3!"), Ok(Value::Number(64.0)));
+        assert_eq!(evaluator.evaluate_str("171!"), Err(CalcError::Overflow { token: "!".to_string(), span: Span::new(3, 4) }));
+        let not_natural = |start| Err(CalcError::DomainError { token: "!".to_string(), span: Span::new(start, start + 1),
+            message: "Factorial is only defined for non-negative integers, use gamma for other numbers".to_string() });
+        assert_eq!(evaluator.evaluate_str("2.5!"), not_natural(3));
+        assert_eq!(evaluator.evaluate_str("(-1)!"), not_natural(4));
+    }
+
+    #[test]
+    fn gamma_extends_factorial() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("gamma 5"), Ok(Value::Number(24.0)));
+        assert!((evaluator.evaluate_str("gamma 0.5 ^ 2").unwrap().to_f64() - std::f64::consts::PI).abs() < 1e-13);
+        assert_eq!(evaluator.evaluate_str("gamma 0"), Err(CalcError::DomainError { token: "gamma".to_string(), span: Span::new(0, 5),
+            message: "Gamma function is undefined for zero and negative integers".to_string() }));
+    }
+
+    #[test]
+    fn permutations_and_combinations() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("5 nPr 2"), Ok(Value::Number(20.0)));
+        assert_eq!(evaluator.evaluate_str("nCr(5, 2)"), Ok(Value::Number(10.0)));
+        assert_eq!(evaluator.evaluate_str("52 nCr 5"), Ok(Value::Number(2598960.0)));
+        assert_eq!(evaluator.evaluate_str("2 nCr 3"), Err(CalcError::DomainError { token: "nCr".to_string(), span: Span::new(2, 5),
+            message: "Cannot choose more items than there are".to_string() }));
+        assert_eq!(evaluator.evaluate_str("2 nPr -1"), Err(CalcError::DomainError { token: "nPr".to_string(), span: Span::new(2, 5),
+            message: "Arguments of permutations and combinations must be non-negative integers".to_string() }));
+        assert_eq!(evaluator.evaluate_str("1000000000 nPr 100000000"),
+            Err(CalcError::Overflow { token: "nPr".to_string(), span: Span::new(11, 14) }));
+    }
+
+    #[test]
+    fn percent_is_taken_of_the_number_before_it() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("25%"), Ok(Value::Number(0.25)));
+        // a percentage added or subtracted is taken of the number before it
+        assert_eq!(evaluator.evaluate_str("200 + 10%"), Ok(Value::Number(220.0)));
+        assert_eq!(evaluator.evaluate_str("200 - 10%"), Ok(Value::Number(180.0)));
+        // a percentage multiplied or divided is a fraction
+        assert_eq!(evaluator.evaluate_str("50 * 10%"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluator.evaluate_str("50 / 10%"), Ok(Value::Number(500.0)));
+        assert_eq!(evaluator.evaluate_str("%"), Err(CalcError::StackUnderflow { token: "%".to_string(), span: Span::new(0, 1) }));
+    }
+
+    #[test]
+    fn functors_compute_decimals_to_the_precision() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Decimal(40));
+        let decimal = |text| Ok(Value::Decimal(BigDecimal::from_str(text).unwrap()));
+        assert_eq!(evaluator.evaluate_str("0.1 + 0.2"), decimal("0.3"));
+        assert_eq!(evaluator.evaluate_str("1/3"), decimal("0.3333333333333333333333333333333333333333"));
+        assert_eq!(evaluator.evaluate_str("√2"), decimal("1.41421356237309504880168872420969807857"));
+        assert_eq!(evaluator.evaluate_str("2^64 + 25!"), decimal("15511228490075059693551616"));
+        assert_eq!(evaluator.evaluate_str("log10 1000 + log2 8"), decimal("6"));
+        assert_eq!(evaluator.evaluate_str("200 + 10%"), decimal("220"));
+        assert_eq!(evaluator.evaluate_str("round(2.5) + round(1234, -2)"), decimal("1203"));
+        assert_eq!(evaluator.evaluate_str("1/0"), Err(CalcError::DivisionByZero { token: "/".to_string(), span: Span::new(1, 2) }));
+        evaluator.set_angle_mode(AngleMode::Deg);
+        assert_eq!(evaluator.evaluate_str("sin 30 + atan2(1, -1)"), decimal("135.5"));
+    }
+
+    #[test]
+    fn huge_products_overflow_without_expanding_their_arguments() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Decimal(32));
+        assert_eq!(evaluator.evaluate_str("(1e100000000)!"), Err(CalcError::Overflow { token: "!".to_string(), span: Span::new(13, 14) }));
+        assert_eq!(evaluator.evaluate_str("(1e10000000)!"), Err(CalcError::Overflow { token: "!".to_string(), span: Span::new(12, 13) }));
+        assert_eq!(evaluator.evaluate_str("1e100000000 nCr 2"),
+            Err(CalcError::Overflow { token: "nCr".to_string(), span: Span::new(12, 15) }));
+        assert_eq!(evaluator.evaluate_str("gamma 1e100000000"),
+            Err(CalcError::Overflow { token: "gamma".to_string(), span: Span::new(0, 5) }));
+        assert_eq!(evaluator.evaluate_str("gamma(-2)"), Err(CalcError::DomainError { token: "gamma".to_string(), span: Span::new(0, 5),
+            message: "Gamma function is undefined for zero and negative integers".to_string() }));
+    }
+
+    #[test]
+    fn constants_are_computed_to_the_precision() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Decimal(40));
+        assert_eq!(evaluator.evaluate_str("π"), Ok(Value::Decimal(decimal::pi(40))));
+        assert_eq!(evaluator.evaluate_str("e"), decimal::exp(&BigDecimal::from(1), 40).map(Value::Decimal));
+        assert_eq!(evaluator.evaluate_str("1.2345678901234567890123456789").unwrap().to_string(), "1.2345678901234567890123456789");
+    }
+
+    fn fraction(numerator: i64, denominator: i64) -> Result<Value, CalcError> {
+        Ok(Value::Rational(BigRational::new(numerator.into(), denominator.into())))
+    }
+
+    #[test]
+    fn arithmetic_keeps_fractions_exact() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Rational);
+        assert_eq!(evaluator.evaluate_str("1/3 + 1/6"), fraction(1, 2));
+        assert_eq!(evaluator.evaluate_str("0.1 + 0.2"), fraction(3, 10));
+        assert_eq!(evaluator.evaluate_str("1/3 * 3"), fraction(1, 1));
+        assert_eq!(evaluator.evaluate_str("(2/3)^3 - 2^-3"), fraction(37, 216));
+        assert_eq!(evaluator.evaluate_str("-1/3 mod 1/4"), fraction(-1, 12));
+        assert_eq!(evaluator.evaluate_str("200 + 10%"), fraction(220, 1));
+        assert_eq!(evaluator.evaluate_str("1/(1/2 - 1/2)"), Err(CalcError::DivisionByZero { token: "/".to_string(), span: Span::new(1, 2) }));
+    }
+
+    #[test]
+    fn functors_keep_fractions_exact() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Rational);
+        assert_eq!(evaluator.evaluate_str("⅟(1/7) - 1/7²"), fraction(342, 49));
+        assert_eq!(evaluator.evaluate_str("√(9/16) + abs(-1/4)"), fraction(1, 1));
+        assert_eq!(evaluator.evaluate_str("avg(1/2, 1/3) + max(1/3, 1/4)"), fraction(3, 4));
+        assert_eq!(evaluator.evaluate_str("25! / 24!"), fraction(25, 1));
+        assert_eq!(evaluator.evaluate_str("10 nCr 3 + 5 nPr 2"), fraction(140, 1));
+        assert_eq!(evaluator.evaluate_str("(1/2)!"), Err(CalcError::DomainError { token: "!".to_string(), span: Span::new(5, 6),
+            message: "Factorial is only defined for non-negative integers, use gamma for other numbers".to_string() }));
+    }
+
+    #[test]
+    fn irrational_results_are_computed_with_f64() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Rational);
+        assert_eq!(evaluator.evaluate_str("√2 * √2"), Ok(Value::Number(2.0000000000000004)));
+        assert_eq!(evaluator.evaluate_str("2^(1/2) * 1/3"), Ok(Value::Number(2f64.sqrt() / 3.0)));
+    }
+
+    fn complex(re: f64, im: f64) -> Result<Value, CalcError> {
+        Ok(Value::Complex(Complex64::new(re, im)))
+    }
+
+    #[test]
+    fn arithmetic_of_complex_numbers() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Complex);
+        assert_eq!(evaluator.evaluate_str("i^2"), complex(-1.0, 0.0));
+        assert_eq!(evaluator.evaluate_str("(1 + 2i) * (3 - i)"), complex(5.0, 5.0));
+        assert_eq!(evaluator.evaluate_str("(1 + i)^2"), complex(0.0, 2.0));
+        assert_eq!(evaluator.evaluate_str("1 / (1 + i)"), complex(0.5, -0.5));
+        assert_eq!(evaluator.evaluate_str("-i"), complex(0.0, -1.0));
+        assert_eq!(evaluator.evaluate_str("1 / (i - i)"), Err(CalcError::DivisionByZero { token: "/".to_string(), span: Span::new(2, 3) }));
+        assert_eq!(evaluator.evaluate_str("0^i"), Err(CalcError::DomainError { token: "^".to_string(), span: Span::new(1, 2),
+            message: "Power of zero to a complex number is undefined".to_string() }));
+    }
+
+    #[test]
+    fn functors_of_complex_numbers() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Complex);
+        // a negative number has a square root
+        assert_eq!(evaluator.evaluate_str("√-4"), complex(0.0, 2.0));
+        assert_eq!(evaluator.evaluate_str("abs(3 + 4i)"), complex(5.0, 0.0));
+        // functors of real numbers accept complex numbers without an imaginary part
+        assert_eq!(evaluator.evaluate_str("5!"), complex(120.0, 0.0));
+        assert_eq!(evaluator.evaluate_str("(2i)!"), Err(CalcError::DomainError { token: "!".to_string(), span: Span::new(4, 5),
+            message: "The function is only defined for real numbers".to_string() }));
+    }
+
+    #[test]
+    fn imaginary_unit_is_unknown_in_other_modes() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate_str("i"), Err(CalcError::UnknownConstant { token: "i".to_string(), span: Span::new(0, 1) }));
+        assert_eq!(evaluator.evaluate_str("√-4"), Err(CalcError::DomainError { token: "√".to_string(), span: Span::new(0, 1),
+            message: "Square root of negative number is undefined".to_string() }));
+    }
+
+    #[test]
+    fn functors_compute_integers_in_programmer_mode() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_number_mode(NumberMode::Integer(IntegerMode::default()));
+        // fractions are truncated toward zero
+        assert_eq!(evaluator.evaluate_str("7 / 2"), Ok(Value::Integer(3)));
+        assert_eq!(evaluator.evaluate_str("-7 / 2"), Ok(Value::Integer(-3)));
+        assert_eq!(evaluator.evaluate_str("29e-1 * 2"), Err(CalcError::InvalidToken { token: "29e-1".to_string(), span: Span::new(0, 5) }));
+        assert_eq!(evaluator.evaluate_str("2 * 2.9"), Err(CalcError::InvalidToken { token: "2.9".to_string(), span: Span::new(4, 7) }));
+        assert_eq!(evaluator.evaluate_str("2.0 * 1.5e1"), Ok(Value::Integer(30)));
+        assert_eq!(evaluator.evaluate_str("√10 + 5!"), Ok(Value::Integer(123)));
+        // results are wrapped to the word
+        assert_eq!(evaluator.evaluate_str("2^63"), Ok(Value::Integer(i64::MIN.into())));
+        assert_eq!(evaluator.evaluate_str("9223372036854775807 + 1"), Ok(Value::Integer(i64::MIN.into())));
+        assert_eq!(evaluator.evaluate_str("1 / 0"), Err(CalcError::DivisionByZero { token: "/".to_string(), span: Span::new(2, 3) }));
+        assert_eq!(evaluator.evaluate_str("π"), Err(CalcError::UnknownConstant { token: "π".to_string(), span: Span::new(0, 1) }));
+    }
+}
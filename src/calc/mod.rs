@@ -3,9 +3,23 @@ mod calculator;
 mod functions;
 mod context;
 mod evaluator;
+mod tokenizer;
+mod ast;
+mod parser;
+mod error;
+mod user_function;
+mod value;
+mod decimal;
 
 pub use self::calculator::*;
 pub use self::evaluator::*;
+pub use self::error::*;
+pub use self::functions::*;
+pub use self::context::*;
+pub use self::tokenizer::*;
+pub use self::ast::*;
+pub use self::user_function::*;
+pub use self::value::*;
 
 
 // mod calc {
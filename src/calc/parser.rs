@@ -0,0 +1,393 @@
+use super::functions::*;
+use super::tokenizer::*;
+use super::ast::*;
+use super::error::*;
+use super::context::*;
+use super::value::*;
+use super::is_decimal;
+
+/// name of the functor negating an operand, unary minus is parsed to it
+const NEGATE: &str = "±";
+
+/// deepest nesting of brackets, functors and calls in an expression, a deeper one is rejected
+/// so that neither parsing nor evaluating it can overflow the stack
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+/// Build an abstract syntax tree from tokens.
+/// Functor priorities and associativity are the same as the ones used by the evaluator,
+/// user functions are looked up in the given context.
+pub struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    context: &'a Context,
+    /// number of expressions being parsed inside each other
+    depth: usize,
+    /// number of levels of the last parsed expression, a single operand has one
+    height: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<Token>, context: &'a Context) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            context,
+            depth: 0,
+            height: 0,
+        }
+    }
+
+    /// a name used by a functor or a constant cannot be used for a variable or a user function
+    fn is_reserved(&self, token: &Token) -> bool {
+        token.kind != TokenKind::Identifier ||
+            self.context.library.get_functor(&token.text).is_some() ||
+            self.context.library.has_constant(&token.text)
+    }
+
+    /// a literal number, an integer in programmer mode is written in its radix
+    fn is_number(&self, text: &str) -> bool {
+        match self.context.number_mode {
+            NumberMode::Integer(mode) if mode.radix != Radix::Dec => mode.parse(text).is_some(),
+            // an imaginary number is only valid in complex mode, it is checked when it is evaluated
+            _ => is_decimal(text) || text.strip_suffix('i').is_some_and(is_decimal),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek().is_some_and(|t| t.text == text)
+    }
+
+    fn unexpected_end(&self) -> CalcError {
+        let end = self.tokens.last().map_or(0, |t| t.span.end);
+        CalcError::UnexpectedEnd { span: Span::new(end, end) }
+    }
+
+    /// record the height of an expression built by the given token, it is rejected if it is too high
+    fn raise(&mut self, height: usize, token: &Token) -> Result<(), CalcError> {
+        if height > MAX_NESTING_DEPTH {
+            return Err(CalcError::TooDeep { token: token.text.clone(), span: token.span });
+        }
+        self.height = height;
+        Ok(())
+    }
+
+    /// take the close bracket of a group or an argument list opened by the given token
+    fn expect_close_bracket(&mut self, open: &Token) -> Result<(), CalcError> {
+        match self.next() {
+            Some(t) if t.text == ")" => Ok(()),
+            Some(t) => Err(CalcError::UnexpectedToken { token: t.text, span: t.span }),
+            None => Err(CalcError::UnbalancedBracket { token: open.text.clone(), span: open.span }),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Expr, CalcError> {
+        let is_assignment = self.tokens.len() > 1 && self.tokens[1].text == "=";
+        let expr = if is_assignment {
+            self.parse_assignment()?
+        }
+        else {
+            self.parse_expression(i32::MAX)?
+        };
+        match self.next() {
+            Some(t) if t.text == ")" => Err(CalcError::UnbalancedBracket { token: t.text, span: t.span }),
+            Some(t) => Err(CalcError::UnexpectedToken { token: t.text, span: t.span }),
+            None => Ok(expr),
+        }
+    }
+
+    /// parse either a function definition such as f(x, y) = x^2 + y or an expression
+    pub fn parse_statement(mut self) -> Result<Statement, CalcError> {
+        if !self.is_definition() {
+            return self.parse().map(Statement::Expression);
+        }
+
+        let name = self.next().unwrap();
+        if self.is_reserved(&name) {
+            return Err(CalcError::InvalidAssignment { token: name.text, span: name.span });
+        }
+        // skip the open bracket
+        self.next();
+
+        let mut params: Vec<String> = Vec::new();
+        if self.peek_is(")") {
+            self.next();
+        }
+        else {
+            loop {
+                // the close bracket is known to exist, so there is always a next token here
+                let token = self.next().unwrap();
+                if self.is_reserved(&token) || params.contains(&token.text) {
+                    return Err(CalcError::InvalidAssignment { token: token.text, span: token.span });
+                }
+                params.push(token.text);
+                let separator = self.next().unwrap();
+                if separator.text == ")" {
+                    break;
+                }
+                if separator.text != "," {
+                    return Err(CalcError::UnexpectedToken { token: separator.text, span: separator.span });
+                }
+            }
+        }
+        // skip the equal sign
+        self.next();
+
+        let body = self.parse()?;
+        Ok(Statement::Function { name: name.text, params, body, span: name.span })
+    }
+
+    /// a definition starts with a name followed by a bracketed parameter list then an equal sign
+    fn is_definition(&self) -> bool {
+        if self.tokens.len() < 3 || self.tokens[0].kind != TokenKind::Identifier || self.tokens[1].text != "(" {
+            return false;
+        }
+        let close = self.tokens.iter().position(|t| t.text == ")");
+        close.is_some_and(|i| self.tokens.get(i + 1).is_some_and(|t| t.text == "="))
+    }
+
+    /// parse an assignment such as x = 3 * 2, only a name which is not used by a functor or a constant can be assigned
+    fn parse_assignment(&mut self) -> Result<Expr, CalcError> {
+        let target = self.next().unwrap();
+        if self.is_reserved(&target) {
+            return Err(CalcError::InvalidAssignment { token: target.text, span: target.span });
+        }
+        // skip the equal sign
+        self.next();
+
+        let value = self.parse_expression(i32::MAX)?;
+        self.raise(self.height + 1, &target)?;
+        Ok(Expr::Assign { name: target.text, value: Box::new(value), span: target.span })
+    }
+
+    /// parse an expression which contains only functors binding tighter than the given priority
+    fn parse_expression(&mut self, limit: i32) -> Result<Expr, CalcError> {
+        // a sign does not add a level to the tree, so the recursion is limited on its own
+        if self.depth >= MAX_NESTING_DEPTH {
+            let token = &self.tokens[self.position - 1];
+            return Err(CalcError::TooDeep { token: token.text.clone(), span: token.span });
+        }
+        self.depth += 1;
+        let expr = self.parse_chain(limit);
+        self.depth -= 1;
+        expr
+    }
+
+    /// parse an operand followed by the functors applied to it,
+    /// a chain such as 1 + 2 + 3 is built by a loop and its height is checked at each functor
+    fn parse_chain(&mut self, limit: i32) -> Result<Expr, CalcError> {
+        let mut left = self.parse_operand()?;
+        let mut height = self.height;
+
+        while let Some(token) = self.peek() {
+            let functor = match self.context.get_functor(&token.text) {
+                Some(f) => f,
+                None => break,
+            };
+            if functor.id() == ID_OPEN_BRACKET || functor.id() == ID_CLOSE_BRACKET || functor.id() == ID_COMMA {
+                break;
+            }
+            let binds = functor.priority() < limit ||
+                (functor.priority() == limit && functor.right_associative());
+            if !binds {
+                break;
+            }
+
+            if is_postfix(functor.as_ref()) {
+                let token = self.next().unwrap();
+                height += 1;
+                self.raise(height, &token)?;
+                left = Expr::Unary { name: token.text, arg: Box::new(left), span: token.span };
+            }
+            else if functor.notation() == Notation::Infix {
+                let token = self.next().unwrap();
+                let right = self.parse_expression(functor.priority())?;
+                height = height.max(self.height) + 1;
+                self.raise(height, &token)?;
+                left = Expr::Binary { name: token.text, left: Box::new(left), right: Box::new(right), span: token.span };
+            }
+            else {
+                // a prefix functor cannot follow an operand
+                break;
+            }
+        }
+
+        self.height = height;
+        Ok(left)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr, CalcError> {
+        let token = self.next().ok_or_else(|| self.unexpected_end())?;
+        self.height = 1;
+
+        if self.is_number(&token.text) {
+            return Ok(Expr::Number { text: token.text, span: token.span });
+        }
+        if token.kind == TokenKind::Number {
+            return Err(CalcError::InvalidToken { token: token.text, span: token.span });
+        }
+        if self.context.library.has_constant(&token.text) {
+            return Ok(Expr::Constant { name: token.text, span: token.span });
+        }
+
+        let functor = match self.context.get_functor(&token.text) {
+            Some(f) => f,
+            None if token.kind == TokenKind::Identifier && self.peek_is("(") => {
+                // a call to a function which is not defined yet such as a recursive call in its own body,
+                // the function is looked up and its arguments are counted when the expression is evaluated
+                return self.parse_call(token, None);
+            }
+            None if token.kind == TokenKind::Identifier => {
+                // a name which is neither a functor nor a constant refers to a variable
+                return Ok(Expr::Variable { name: token.text, span: token.span });
+            }
+            None => return Err(CalcError::UnknownFunction { token: token.text, span: token.span }),
+        };
+        if functor.id() == ID_SUB || functor.id() == ID_ADD {
+            // a sign before an operand, it binds looser than power so that -3^2 is -(3^2)
+            let arg = self.parse_expression(PRIORITY_POWER)?;
+            if functor.id() == ID_ADD {
+                return Ok(arg);
+            }
+            self.raise(self.height + 1, &token)?;
+            return Ok(Expr::Unary { name: NEGATE.to_string(), arg: Box::new(arg), span: token.span });
+        }
+        if functor.id() == ID_OPEN_BRACKET {
+            let inner = self.parse_expression(i32::MAX)?;
+            self.expect_close_bracket(&token)?;
+            self.raise(self.height + 1, &token)?;
+            return Ok(Expr::Group(Box::new(inner)));
+        }
+        if token.kind == TokenKind::Identifier && self.peek_is("(") {
+            return self.parse_call(token, Some(functor));
+        }
+        if functor.notation() == Notation::Prefix {
+            let arg = self.parse_expression(functor.priority())?;
+            self.raise(self.height + 1, &token)?;
+            return Ok(Expr::Unary { name: token.text, arg: Box::new(arg), span: token.span });
+        }
+        if functor.notation() == Notation::Function {
+            // a function must be followed by its argument list
+            return match self.next() {
+                Some(t) => Err(CalcError::UnexpectedToken { token: t.text, span: t.span }),
+                None => Err(self.unexpected_end()),
+            };
+        }
+
+        if functor.id() == ID_CLOSE_BRACKET {
+            return Err(CalcError::UnbalancedBracket { token: token.text, span: token.span });
+        }
+        if functor.id() == ID_COMMA {
+            return Err(CalcError::UnexpectedToken { token: token.text, span: token.span });
+        }
+        Err(CalcError::StackUnderflow { token: token.text, span: token.span })
+    }
+
+    /// parse a bracketed argument list such as (1, 2) following a function name,
+    /// the number of arguments is checked only if the function is already known
+    fn parse_call(&mut self, name: Token, functor: Option<Box<dyn Functor>>) -> Result<Expr, CalcError> {
+        let open = self.next().unwrap();
+
+        let mut args = Vec::new();
+        let mut height = 0;
+        if !self.peek_is(")") {
+            loop {
+                args.push(self.parse_expression(i32::MAX)?);
+                height = height.max(self.height);
+                if !self.peek_is(",") {
+                    break;
+                }
+                self.next();
+            }
+        }
+        self.expect_close_bracket(&open)?;
+        if let Some(functor) = functor {
+            check_arg_count(functor.as_ref(), args.len()).map_err(|e| e.at(&name.text, name.span))?;
+        }
+        self.raise(height + 1, &name)?;
+        Ok(Expr::Call { name: name.text, args, span: name.span })
+    }
+}
+
+/// integers are written in the radix of the programmer mode, other numbers are decimal
+fn radix_of(context: &Context) -> Radix {
+    match context.number_mode {
+        NumberMode::Integer(mode) => mode.radix,
+        _ => Radix::Dec,
+    }
+}
+
+/// parse a whole expression string such as "2*(3+sin 1)" into an abstract syntax tree
+pub fn parse(expression: &str, context: &Context) -> Result<Expr, CalcError> {
+    if expression.trim().is_empty() {
+        return Err(CalcError::EmptyInput);
+    }
+    Parser::new(tokenize_in(expression, radix_of(context)), context).parse()
+}
+
+/// parse a line which is either an expression or a function definition such as "f(x) = x^2"
+pub fn parse_statement(line: &str, context: &Context) -> Result<Statement, CalcError> {
+    if line.trim().is_empty() {
+        return Err(CalcError::EmptyInput);
+    }
+    Parser::new(tokenize_in(line, radix_of(context)), context).parse_statement()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str, start: usize) -> Box<Expr> {
+        Box::new(Expr::Number { text: text.to_string(), span: Span::new(start, start + text.len()) })
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        let context = Context::new();
+        let power = |left, right, start| Expr::Binary { name: "^".to_string(), left, right, span: Span::new(start, start + 1) };
+        assert_eq!(parse("2^3^2", &context), Ok(power(number("2", 0), Box::new(power(number("3", 2), number("2", 4), 3)), 1)));
+
+        let minus = |left, right, start| Expr::Binary { name: "-".to_string(), left, right, span: Span::new(start, start + 1) };
+        assert_eq!(parse("5-3-1", &context), Ok(minus(Box::new(minus(number("5", 0), number("3", 2), 1)), number("1", 4), 3)));
+    }
+
+    #[test]
+    fn report_where_the_expression_is_invalid() {
+        let context = Context::new();
+        assert_eq!(parse("  ", &context), Err(CalcError::EmptyInput));
+        assert_eq!(parse("(1 + 2", &context), Err(CalcError::UnbalancedBracket { token: "(".to_string(), span: Span::new(0, 1) }));
+        assert_eq!(parse("1 + 2)", &context), Err(CalcError::UnbalancedBracket { token: ")".to_string(), span: Span::new(5, 6) }));
+        assert_eq!(parse("1 +", &context), Err(CalcError::UnexpectedEnd { span: Span::new(3, 3) }));
+        assert_eq!(parse("1 2", &context), Err(CalcError::UnexpectedToken { token: "2".to_string(), span: Span::new(2, 3) }));
+        assert_eq!(parse("1..2", &context), Err(CalcError::InvalidToken { token: "1..2".to_string(), span: Span::new(0, 4) }));
+        assert_eq!(parse("sin = 1", &context), Err(CalcError::InvalidAssignment { token: "sin".to_string(), span: Span::new(0, 3) }));
+        assert!(matches!(parse("hypot(1)", &context), Err(CalcError::ArgumentCount { expected: 2, found: 1, .. })));
+    }
+
+    #[test]
+    fn parse_function_definitions() {
+        let context = Context::new();
+        let statement = parse_statement("f(x, y) = x * y", &context).unwrap();
+        let Statement::Function { name, params, span, .. } = statement else {
+            panic!("{:?} is not a definition", statement);
+        };
+        assert_eq!((name.as_str(), params, span), ("f", vec!["x".to_string(), "y".to_string()], Span::new(0, 1)));
+
+        assert!(matches!(parse_statement("f(x) + 1", &context), Ok(Statement::Expression(Expr::Binary { .. }))));
+        assert_eq!(parse_statement("f(x, x) = x", &context),
+            Err(CalcError::InvalidAssignment { token: "x".to_string(), span: Span::new(5, 6) }));
+        assert_eq!(parse_statement("ln(x) = x", &context),
+            Err(CalcError::InvalidAssignment { token: "ln".to_string(), span: Span::new(0, 2) }));
+    }
+}
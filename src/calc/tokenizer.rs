@@ -0,0 +1,192 @@
+use std::iter::{Enumerate, Peekable};
+use std::str::Chars;
+use super::error::Span;
+use super::value::Radix;
+
+/// Kind of a token, it tells a number from a name or a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// a decimal number such as 12 or 3.14, it may be written in scientific notation such as 6.022e23
+    /// or be an imaginary number such as 2i, an integer in another radix is a word of its digits such as 1A
+    Number,
+    /// a name made of ascii letters, digits and underscores such as sin or ln,
+    /// it may be qualified by namespaces separated by dots such as stats.mean
+    Identifier,
+    /// any other single character such as +, (, √, ² or π
+    Symbol,
+}
+
+/// A number, a name or a symbol of an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// what the token is
+    pub kind: TokenKind,
+    /// the token as written in the expression
+    pub text: String,
+    /// character position of the token in the expression
+    pub span: Span,
+}
+
+impl Token {
+    /// a token of the given kind written at the given span
+    pub fn new(kind: TokenKind, text: String, span: Span) -> Self {
+        Self {
+            kind,
+            text,
+            span,
+        }
+    }
+}
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || c == '.'
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// a word made only of digits of a radix other than decimal, such as FF or 1A in hexadecimal
+fn radix_word(first: char, rest: &Peekable<Enumerate<Chars>>, radix: Radix) -> Option<String> {
+    if radix == Radix::Dec || !radix.is_digit(first) {
+        return None;
+    }
+    let mut word = first.to_string();
+    word.extend(rest.clone().map(|(_, c)| c).take_while(|&c| is_identifier_char(c)));
+    word.chars().all(|c| radix.is_digit(c)).then_some(word)
+}
+
+/// split an expression string into tokens that can be put to the evaluator one by one
+pub fn tokenize(expression: &str) -> Vec<Token> {
+    tokenize_in(expression, Radix::Dec)
+}
+
+/// split an expression string into tokens, integers are written in the given radix
+pub fn tokenize_in(expression: &str, radix: Radix) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut iterator = expression.chars().enumerate().peekable();
+
+    while let Some((start, c)) = iterator.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut text = c.to_string();
+        let kind;
+        if let Some(word) = radix_word(c, &iterator, radix) {
+            for _ in 1..word.chars().count() {
+                iterator.next();
+            }
+            text = word;
+            kind = TokenKind::Number;
+        }
+        else if is_number_char(c) {
+            while let Some(&(_, next)) = iterator.peek() {
+                if !is_number_char(next) {
+                    break;
+                }
+                text.push(next);
+                iterator.next();
+            }
+            // an exponent follows only if there is at least one digit after e and its sign,
+            // otherwise e is the constant or the start of a name
+            let mut ahead = iterator.clone();
+            if let Some((_, e @ ('e' | 'E'))) = ahead.next() {
+                let mut exponent = e.to_string();
+                if let Some(&(_, sign @ ('-' | '+'))) = ahead.peek() {
+                    exponent.push(sign);
+                    ahead.next();
+                }
+                if ahead.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+                    while let Some(&(_, digit)) = ahead.peek() {
+                        if !digit.is_ascii_digit() {
+                            break;
+                        }
+                        exponent.push(digit);
+                        ahead.next();
+                    }
+                    text.push_str(&exponent);
+                    iterator = ahead;
+                }
+            }
+            // an imaginary number ends with i unless i is the start of a name
+            let mut ahead = iterator.clone();
+            if let Some((_, 'i')) = ahead.next() {
+                if !ahead.peek().is_some_and(|&(_, c)| is_identifier_char(c)) {
+                    text.push('i');
+                    iterator = ahead;
+                }
+            }
+            kind = TokenKind::Number;
+        }
+        else if is_identifier_start(c) {
+            while let Some(&(_, next)) = iterator.peek() {
+                if next == '.' {
+                    // a dot between two names separates a namespace from a name
+                    let mut ahead = iterator.clone();
+                    ahead.next();
+                    if !ahead.peek().is_some_and(|&(_, c)| is_identifier_start(c)) {
+                        break;
+                    }
+                }
+                else if !is_identifier_char(next) {
+                    break;
+                }
+                text.push(next);
+                iterator.next();
+            }
+            kind = TokenKind::Identifier;
+        }
+        else {
+            kind = TokenKind::Symbol;
+        }
+        let end = start + text.chars().count();
+        tokens.push(Token::new(kind, text, Span::new(start, end)));
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts_and_spans(tokens: &[Token]) -> Vec<(&str, usize, usize)> {
+        tokens.iter().map(|t| (t.text.as_str(), t.span.start, t.span.end)).collect()
+    }
+
+    #[test]
+    fn spans_count_characters_rather_than_bytes() {
+        let tokens = tokenize("2π + ⅟3²");
+        assert_eq!(texts_and_spans(&tokens), vec![("2", 0, 1), ("π", 1, 2), ("+", 3, 4), ("⅟", 5, 6), ("3", 6, 7), ("²", 7, 8)]);
+        assert_eq!(tokens[1].kind, TokenKind::Symbol);
+    }
+
+    #[test]
+    fn split_numbers_and_names() {
+        let tokens = tokenize("stats.mean(6.022e23, 2e, 3i) + x_1.5");
+        assert_eq!(texts_and_spans(&tokens), vec![
+            ("stats.mean", 0, 10), ("(", 10, 11), ("6.022e23", 11, 19), (",", 19, 20), ("2", 21, 22), ("e", 22, 23),
+            (",", 23, 24), ("3i", 25, 27), (")", 27, 28), ("+", 29, 30), ("x_1", 31, 34), (".5", 34, 36),
+        ]);
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(&kinds[..3], &[TokenKind::Identifier, TokenKind::Symbol, TokenKind::Number]);
+        assert!(tokenize(" \t ").is_empty());
+    }
+
+    #[test]
+    fn split_integers_of_a_radix() {
+        let tokens = tokenize_in("1A + FF*-Fx", Radix::Hex);
+        assert_eq!(texts_and_spans(&tokens), vec![("1A", 0, 2), ("+", 3, 4), ("FF", 5, 7), ("*", 7, 8), ("-", 8, 9), ("Fx", 9, 11)]);
+        assert_eq!(tokens[2].kind, TokenKind::Number);
+        assert_eq!(tokens[5].kind, TokenKind::Identifier);
+        // digits of another radix are not a part of the integer
+        assert_eq!(texts_and_spans(&tokenize_in("102", Radix::Bin)), vec![("102", 0, 3)]);
+        assert_eq!(texts_and_spans(&tokenize("FF")), vec![("FF", 0, 2)]);
+        assert_eq!(tokenize("FF")[0].kind, TokenKind::Identifier);
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use super::functions::*;
+use super::context::*;
+use super::ast::*;
+use super::error::*;
+use super::value::*;
+
+/// ids of user functions start from here so that they never collide with built-in ids
+/// nor with the ids of functions registered in a library, which start from `ID_REGISTERED_FUNCTION_BASE`
+pub const ID_USER_FUNCTION_BASE: FunctionId = FunctionId::MAX / 2;
+
+/// deepest nested user function calls allowed before the evaluation is considered as a runaway recursion
+pub const MAX_CALL_DEPTH: usize = 256;
+
+/// deepest nesting of the bodies of user functions calling each other, counted by their heights,
+/// it keeps a recursive function with a large body from overflowing the stack before reaching `MAX_CALL_DEPTH`
+pub const MAX_BODY_DEPTH: usize = 1024;
+
+/// A function defined at runtime such as f(x, y) = x^2 + y
+#[derive(Debug, Clone)]
+pub struct UserFunction {
+    id: FunctionId,
+    name: String,
+    params: Vec<String>,
+    body: Arc<Expr>,
+    height: usize,
+}
+
+impl UserFunction {
+    /// a function with the given id, its body refers to its parameters as variables
+    pub fn new(id: FunctionId, name: String, params: Vec<String>, body: Expr) -> Self {
+        Self {
+            id,
+            name,
+            params,
+            height: body.height(),
+            body: Arc::new(body),
+        }
+    }
+
+    /// the name which the function is called by
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// names of the parameters in the order of the arguments
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// the expression computing the result
+    pub fn body(&self) -> &Expr {
+        &self.body
+    }
+
+    /// the definition such as f(x, y) = x^2 + y, the body is written with the functors of the given library
+    pub fn definition(&self, library: &FunctionLib) -> String {
+        format!("{}({}) = {}", self.name, self.params.join(", "), self.body.display(library))
+    }
+
+    /// bind the arguments on the stack to the parameters, variables with the same names are hidden during the call
+    /// and returned so that they can be restored after it
+    fn bind_args(&self, context: &mut Context) -> Vec<(String, Option<Value>)> {
+        let mut args = Vec::new();
+        for _ in 0..self.params.len() {
+            args.push(context.execution_stack.pop_val().unwrap());
+        }
+        args.reverse();
+
+        let mut hidden = Vec::new();
+        for (param, arg) in self.params.iter().zip(args) {
+            hidden.push((param.clone(), context.variables.insert(param.clone(), arg)));
+        }
+        hidden
+    }
+
+    fn restore_variables(context: &mut Context, hidden: Vec<(String, Option<Value>)>) {
+        for (param, value) in hidden.into_iter().rev() {
+            match value {
+                Some(v) => context.variables.insert(param, v),
+                None => context.variables.remove(&param),
+            };
+        }
+    }
+}
+
+impl Functor for UserFunction {
+    fn execute(&self, context: &mut Context) -> Result<Value, CalcError> {
+        if context.execution_stack.size() < self.params.len() {
+            return Err(CalcError::stack_underflow());
+        }
+        if context.call_depth >= MAX_CALL_DEPTH {
+            return Err(CalcError::RecursionLimit { token: String::new(), span: Span::default() });
+        }
+        if context.body_depth + self.height > MAX_BODY_DEPTH {
+            return Err(CalcError::TooDeep { token: String::new(), span: Span::default() });
+        }
+
+        // the binding is done by other functions to keep the stack frame small, it is nested once for each recursive call
+        let hidden = self.bind_args(context);
+        context.call_depth += 1;
+        context.body_depth += self.height;
+        let result = self.body.evaluate_in(context);
+        context.body_depth -= self.height;
+        context.call_depth -= 1;
+        Self::restore_variables(context, hidden);
+        // positions in the body do not refer to the input being evaluated, the caller binds the error to the call
+        result.map_err(|e| e.detached())
+    }
+    fn priority(&self) -> i32 {
+        PRIORITY_USER_FUNCTION
+    }
+    fn id(&self) -> FunctionId {
+        self.id
+    }
+    fn arg_count(&self) -> i32 {
+        self.params.len() as i32
+    }
+    fn notation(&self) -> Notation {
+        Notation::Function
+    }
+}
+
@@ -0,0 +1,621 @@
+use std::fmt;
+use std::str::FromStr;
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::num_traits::FromPrimitive;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use super::context::AngleMode;
+use super::decimal;
+use super::error::CalcError;
+use super::evaluator::is_decimal;
+
+/// How numbers are represented while an expression is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+    /// binary floating point numbers, fast but 0.1 + 0.2 is not exactly 0.3
+    #[default]
+    Float,
+    /// decimal numbers rounded to the given number of significant digits
+    Decimal(u64),
+    /// exact fractions such as 1/3, functions with irrational results such as sin are computed with f64
+    Rational,
+    /// complex numbers such as 1 + 2i computed with f64, i is the imaginary unit
+    Complex,
+    /// integers of a word size written in a radix for programmers, fractional results are truncated
+    Integer(IntegerMode),
+}
+
+/// Base in which integers are input and displayed in programmer mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radix {
+    /// hexadecimal, the digits above 9 are A to F
+    Hex,
+    /// decimal
+    #[default]
+    Dec,
+    /// octal
+    Oct,
+    /// binary
+    Bin,
+}
+
+impl Radix {
+    /// number of digits, such as 16 for hexadecimal
+    pub fn base(&self) -> u32 {
+        match self {
+            Radix::Hex => 16,
+            Radix::Dec => 10,
+            Radix::Oct => 8,
+            Radix::Bin => 2,
+        }
+    }
+
+    /// whether the character is a digit of the radix, digits above 9 are the capital letters A to F
+    /// so that they are not mistaken for names such as e
+    pub fn is_digit(&self, c: char) -> bool {
+        !c.is_ascii_lowercase() && c.is_digit(self.base())
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Radix::Hex => write!(f, "HEX"),
+            Radix::Dec => write!(f, "DEC"),
+            Radix::Oct => write!(f, "OCT"),
+            Radix::Bin => write!(f, "BIN"),
+        }
+    }
+}
+
+/// How integers are computed in programmer mode, a result out of the range of the word wraps around like in a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegerMode {
+    /// base in which integers are input and displayed
+    pub radix: Radix,
+    /// word size in bits from 1 to 64, it is private so that it is always a valid shift of an i128
+    bits: u32,
+    /// whether the highest bit of the word is the sign, otherwise integers are never negative
+    pub signed: bool,
+}
+
+impl Default for IntegerMode {
+    fn default() -> Self {
+        Self {
+            radix: Radix::Dec,
+            bits: 64,
+            signed: true,
+        }
+    }
+}
+
+impl IntegerMode {
+    /// a mode with a word of the given number of bits, none unless it is from 1 to 64
+    pub fn new(radix: Radix, bits: u32, signed: bool) -> Option<Self> {
+        (1..=64).contains(&bits).then_some(Self { radix, bits, signed })
+    }
+
+    /// word size in bits
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// the mode with the next word size in the order 64, 32, 16, 8
+    pub fn next_word_size(&self) -> Self {
+        let bits = match self.bits {
+            64 => 32,
+            32 => 16,
+            16 => 8,
+            _ => 64,
+        };
+        Self { bits, ..*self }
+    }
+
+    /// the value of the word keeping only the low bits of the integer
+    pub fn wrap(&self, value: i128) -> i128 {
+        let low = value.rem_euclid(1 << self.bits);
+        if self.signed && low >= 1 << (self.bits - 1) {
+            low - (1 << self.bits)
+        } else {
+            low
+        }
+    }
+
+    fn wrap_big(&self, value: &BigInt) -> i128 {
+        // the low 64 bits of the two's complement of the integer are enough for every word size
+        let low = (value & BigInt::from(u64::MAX)).to_u64().unwrap();
+        self.wrap(low as i128)
+    }
+
+    /// parse the digits of an integer in the radix such as FF, a minus sign may be written before them
+    pub fn parse(&self, text: &str) -> Option<i128> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| self.radix.is_digit(c)) {
+            return None;
+        }
+        let value = BigInt::parse_bytes(digits.as_bytes(), self.radix.base())?;
+        Some(self.wrap_big(&if negative { -value } else { value }))
+    }
+
+    /// the digits of an integer in the radix, a negative integer is written as its two's complement except in decimal
+    pub fn format(&self, value: i128) -> String {
+        let value = self.wrap(value);
+        let bits = value.rem_euclid(1 << self.bits);
+        match self.radix {
+            Radix::Hex => format!("{:X}", bits),
+            Radix::Dec => value.to_string(),
+            Radix::Oct => format!("{:o}", bits),
+            Radix::Bin => format!("{:b}", bits),
+        }
+    }
+}
+
+impl fmt::Display for IntegerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.signed { "signed" } else { "unsigned" };
+        write!(f, "{} {}-bit {}", self.radix, self.bits, sign)
+    }
+}
+
+/// How exact fractions are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FractionDisplay {
+    /// numerator and denominator such as 1/3
+    #[default]
+    Fraction,
+    /// decimal number rounded to the given number of significant digits such as 0.3333
+    Decimal(u64),
+}
+
+/// a fraction such as -1/3 which is an operand in rational mode
+pub fn is_fraction(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    match s.split_once('/') {
+        Some((numerator, denominator)) => [numerator, denominator].iter()
+            .all(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())),
+        None => false,
+    }
+}
+
+/// a complex number such as 1 + 2i or -i which is an operand in complex mode
+pub fn is_complex(s: &str) -> bool {
+    s.trim_end().ends_with('i') && parse_complex(s).is_some()
+}
+
+/// parse a complex number such as 1 + 2i, -i or 2.5, spaces are allowed around the sign of the imaginary part
+fn parse_complex(text: &str) -> Option<Complex64> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let real = |part: &str| if is_decimal(part) { part.parse::<f64>().ok().filter(|v| v.is_finite()) } else { None };
+    let Some(imaginary) = text.strip_suffix('i') else {
+        return real(&text).map(Complex64::from);
+    };
+    // the sign of the imaginary part is the last sign which is neither the first character nor the sign of an exponent
+    let split = imaginary.char_indices().rev()
+        .find(|&(i, c)| i > 0 && (c == '+' || c == '-') && !imaginary[..i].ends_with(['e', 'E']))
+        .map_or(0, |(i, _)| i);
+    let (re, im) = imaginary.split_at(split);
+    let re = if re.is_empty() { 0.0 } else { real(re)? };
+    let im = match im {
+        // the coefficient 1 of the imaginary unit is not written
+        "" | "+" => 1.0,
+        "-" => -1.0,
+        im => real(im)?,
+    };
+    Some(Complex64::new(re, im))
+}
+
+/// Kind of a value, a functor reports a type error when it gets a value of a kind it does not accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// a number of any number mode
+    Number,
+    /// an exact integer
+    Integer,
+    /// true or false
+    Boolean,
+    /// a text
+    String,
+    /// a list of values of any kind
+    List,
+}
+
+impl ValueKind {
+    /// whether values of the kind are accepted by arithmetic functors, integers are numbers too
+    pub fn is_number(&self) -> bool {
+        matches!(self, ValueKind::Number | ValueKind::Integer)
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueKind::Number => write!(f, "number"),
+            ValueKind::Integer => write!(f, "integer"),
+            ValueKind::Boolean => write!(f, "boolean"),
+            ValueKind::String => write!(f, "string"),
+            ValueKind::List => write!(f, "list"),
+        }
+    }
+}
+
+/// A value computed by functors and stored in the execution stack
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// a binary floating point number, it is the number of float mode
+    Number(f64),
+    /// a decimal number of decimal mode
+    Decimal(BigDecimal),
+    /// an exact fraction of rational mode
+    Rational(BigRational),
+    /// a complex number of complex mode
+    Complex(Complex64),
+    /// an exact integer which is kept as an integer in every number mode,
+    /// it is wide enough for the words of programmer mode either signed or not
+    Integer(i128),
+    /// true or false
+    Boolean(bool),
+    /// a text
+    String(String),
+    /// a list of values of any kind
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// parse a decimal number such as 6.022e23 or a fraction such as 1/3 to a value of the given mode,
+    /// an integer in programmer mode is written in the radix of the mode
+    pub fn parse(text: &str, mode: NumberMode) -> Option<Value> {
+        if let NumberMode::Integer(integer_mode) = mode {
+            if let Some(value) = integer_mode.parse(text) {
+                return Some(Value::Integer(value));
+            }
+        }
+        if is_fraction(text) {
+            let fraction = BigRational::from_str(text).ok()?;
+            return Value::Rational(fraction).to_mode(mode).ok();
+        }
+        match mode {
+            // a number too large for f64 such as 1e400 is not parsed to an infinity
+            NumberMode::Float => text.parse::<f64>().ok().filter(|v| v.is_finite()).map(Value::Number),
+            NumberMode::Decimal(precision) => BigDecimal::from_str(text).ok()
+                .map(|v| Value::Decimal(decimal::round(&v, precision))),
+            NumberMode::Rational => BigDecimal::from_str(text).ok().and_then(|v| match decimal::to_rational(&v) {
+                Some(fraction) => Some(Value::Rational(fraction)),
+                // a number with too large an exponent to be kept exactly is approximated like by to_mode,
+                // unless it is too large for f64 too
+                None => Some(decimal::to_f64(&v)).filter(|v| v.is_finite()).map(Value::Number),
+            }),
+            NumberMode::Complex => parse_complex(text).map(Value::Complex),
+            // a decimal number such as 2.5 is not an integer, a number such as 1.5e3 is
+            NumberMode::Integer(_) => BigDecimal::from_str(text).ok().filter(BigDecimal::is_integer)
+                .and_then(|v| Value::Decimal(v).to_mode(mode).ok()),
+        }
+    }
+
+    /// convert a number computed with f64 to a value of the given mode
+    pub fn from_f64(value: f64, mode: NumberMode) -> Result<Value, CalcError> {
+        match mode {
+            NumberMode::Float => Ok(Value::Number(value)),
+            NumberMode::Decimal(precision) => decimal::from_f64(value).map(|v| Value::Decimal(decimal::round(&v, precision))),
+            // 0.1 is converted to 1/10 rather than to the binary fraction nearest to it
+            NumberMode::Rational => decimal::from_f64(value)
+                .map(|v| decimal::to_rational(&v).map_or(Value::Number(value), Value::Rational)),
+            NumberMode::Complex => Ok(Value::Complex(Complex64::from(value))),
+            NumberMode::Integer(integer_mode) => BigInt::from_f64(value.trunc())
+                .map(|v| Value::Integer(integer_mode.wrap_big(&v)))
+                .ok_or_else(CalcError::overflow),
+        }
+    }
+
+    /// the kind of the value checked by functors
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Number(_) | Value::Decimal(_) | Value::Rational(_) | Value::Complex(_) => ValueKind::Number,
+            Value::Integer(_) => ValueKind::Integer,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::String(_) => ValueKind::String,
+            Value::List(_) => ValueKind::List,
+        }
+    }
+
+    /// the value converted to the given mode, decimals are rounded to the precision of the mode,
+    /// values which are not numbers of a number mode are kept as they are and the items of a list are converted
+    pub fn to_mode(&self, mode: NumberMode) -> Result<Value, CalcError> {
+        match (self, mode) {
+            (Value::Integer(v), NumberMode::Integer(integer_mode)) => Ok(Value::Integer(integer_mode.wrap(*v))),
+            (Value::Integer(_) | Value::Boolean(_) | Value::String(_), _) => Ok(self.clone()),
+            (Value::List(items), mode) => items.iter().map(|v| v.to_mode(mode)).collect::<Result<_, _>>().map(Value::List),
+            (Value::Complex(v), NumberMode::Complex) => Ok(Value::Complex(*v)),
+            (Value::Complex(v), mode) if v.im == 0.0 => Value::from_f64(v.re, mode),
+            (Value::Complex(_), _) => Err(CalcError::domain_error("Complex numbers are only supported in complex mode")),
+            (value, NumberMode::Complex) => Ok(Value::Complex(Complex64::from(value.to_f64()))),
+            // fractional parts are truncated toward zero
+            (Value::Decimal(v), NumberMode::Integer(integer_mode)) => {
+                let (integer, _) = v.with_scale_round(0, RoundingMode::Down).into_bigint_and_exponent();
+                Ok(Value::Integer(integer_mode.wrap_big(&integer)))
+            }
+            (Value::Rational(v), NumberMode::Integer(integer_mode)) => Ok(Value::Integer(integer_mode.wrap_big(&v.to_integer()))),
+            (Value::Number(v), mode) => Value::from_f64(*v, mode),
+            (Value::Decimal(v), NumberMode::Decimal(precision)) => Ok(Value::Decimal(decimal::round(v, precision))),
+            (Value::Decimal(v), NumberMode::Float) => Ok(Value::Number(decimal::to_f64(v))),
+            (Value::Decimal(v), NumberMode::Rational) =>
+                Ok(decimal::to_rational(v).map_or_else(|| Value::Number(decimal::to_f64(v)), Value::Rational)),
+            (Value::Rational(v), NumberMode::Rational) => Ok(Value::Rational(v.clone())),
+            (Value::Rational(_), NumberMode::Float) => Ok(Value::Number(self.to_f64())),
+            (Value::Rational(v), NumberMode::Decimal(precision)) => Ok(Value::Decimal(decimal::from_rational(v, precision))),
+        }
+    }
+
+    /// the nearest f64, digits beyond the precision of f64 are lost,
+    /// it is NaN for values which are not numbers, functors check the kinds of their arguments before
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Number(v) => *v,
+            Value::Decimal(v) => decimal::to_f64(v),
+            Value::Rational(v) => v.to_f64().unwrap_or(f64::NAN),
+            // the imaginary part is lost
+            Value::Complex(v) => v.re,
+            Value::Integer(v) => *v as f64,
+            Value::Boolean(_) | Value::String(_) | Value::List(_) => f64::NAN,
+        }
+    }
+
+    /// the value as a complex number, a real number has no imaginary part
+    pub fn to_complex(&self) -> Complex64 {
+        match self {
+            Value::Complex(v) => *v,
+            _ => Complex64::from(self.to_f64()),
+        }
+    }
+
+    /// the value as a decimal, fractions are divided to the given number of significant digits,
+    /// it fails for infinite numbers and for values which are not numbers
+    pub fn to_decimal(&self, precision: u64) -> Result<BigDecimal, CalcError> {
+        match self {
+            Value::Number(v) => decimal::from_f64(*v),
+            Value::Decimal(v) => Ok(v.clone()),
+            Value::Rational(v) => Ok(decimal::from_rational(v, precision)),
+            Value::Complex(v) => decimal::from_f64(v.re),
+            Value::Integer(v) => Ok(BigDecimal::from(*v)),
+            Value::Boolean(_) | Value::String(_) | Value::List(_) => Err(CalcError::type_error(ValueKind::Number, self.kind())),
+        }
+    }
+
+    /// the value as an exact fraction, none for numbers computed with f64 which are not exact anyway
+    pub fn to_rational(&self) -> Option<BigRational> {
+        match self {
+            Value::Rational(v) => Some(v.clone()),
+            Value::Integer(v) => Some(BigRational::from_integer((*v).into())),
+            _ => None,
+        }
+    }
+
+    /// the text of the value to be displayed, fractions are displayed in the given way
+    pub fn format(&self, display: FractionDisplay) -> String {
+        match (self, display) {
+            (Value::Rational(v), FractionDisplay::Decimal(digits)) =>
+                Value::Decimal(decimal::from_rational(v, digits)).to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// the text of the value to be input again in the given mode, integers are written in the radix of programmer mode
+    pub fn format_in(&self, mode: NumberMode) -> String {
+        match (self, mode) {
+            (Value::Integer(v), NumberMode::Integer(integer_mode)) => integer_mode.format(*v),
+            (Value::List(items), NumberMode::Integer(_)) => {
+                let items: Vec<String> = items.iter().map(|item| item.format_in(mode)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// the text of a complex number in polar form such as 2∠90 for 2i, the angle is in the given angle mode,
+    /// real numbers are written as usual
+    pub fn format_polar(&self, angle_mode: AngleMode) -> String {
+        match self {
+            Value::Complex(v) if v.im != 0.0 => format!("{}∠{}", v.norm(), angle_mode.from_radians(v.arg())),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(v) => write!(f, "{}", v),
+            Value::Decimal(v) => {
+                let v = v.normalized();
+                // very large and very small numbers are written in scientific notation such as 1.5e40
+                if (-10..30).contains(&v.order_of_magnitude()) {
+                    write!(f, "{}", v.to_plain_string())
+                } else {
+                    write!(f, "{}", v.to_scientific_notation())
+                }
+            }
+            // an integer is written without a denominator
+            Value::Rational(v) => write!(f, "{}", v),
+            Value::Complex(v) => {
+                // the coefficient 1 of the imaginary unit is not written such as 2 - i
+                let imaginary = |im: f64| if im == 1.0 { "i".to_string() } else { format!("{}i", im) };
+                if v.im == 0.0 {
+                    write!(f, "{}", v.re)
+                } else if v.re == 0.0 {
+                    let sign = if v.im < 0.0 { "-" } else { "" };
+                    write!(f, "{}{}", sign, imaginary(v.im.abs()))
+                } else {
+                    let sign = if v.im < 0.0 { '-' } else { '+' };
+                    write!(f, "{} {} {}", v.re, sign, imaginary(v.im.abs()))
+                }
+            }
+            // the radix is a setting of programmer mode, so an integer is written in decimal here,
+            // format_in writes it in the radix of the mode
+            Value::Integer(v) => write!(f, "{}", v),
+            Value::Boolean(v) => write!(f, "{}", v),
+            // quoted so that it is not mistaken for a number or a name
+            Value::String(v) => write!(f, "{:?}", v),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_scientific_notation() {
+        assert_eq!(Value::parse("6.022e23", NumberMode::Float), Some(Value::Number(6.022e23)));
+        assert_eq!(Value::parse("2E+2", NumberMode::Float), Some(Value::Number(200.0)));
+        assert_eq!(Value::parse("-1e-3", NumberMode::Rational), Some(Value::Rational(BigRational::new((-1).into(), 1000.into()))));
+        assert_eq!(Value::parse("1.5e3", NumberMode::Decimal(2)), Some(Value::Decimal(BigDecimal::from(1500))));
+        assert_eq!(Value::parse("2.5e1i", NumberMode::Complex), Some(Value::Complex(Complex64::new(0.0, 25.0))));
+        let integer = NumberMode::Integer(IntegerMode::default());
+        assert_eq!(Value::parse("2.5e1", integer), Some(Value::Integer(25)));
+        assert_eq!(Value::parse("2.5", integer), None);
+    }
+
+    #[test]
+    fn numbers_too_large_for_f64_are_not_infinite() {
+        assert_eq!(Value::parse("1e400", NumberMode::Float), None);
+        assert_eq!(Value::parse("1e400i", NumberMode::Complex), None);
+        assert_eq!(Value::parse("1 + 1e400i", NumberMode::Complex), None);
+        // a decimal is not limited by f64
+        assert!(Value::parse("1e400", NumberMode::Decimal(10)).is_some());
+    }
+
+    #[test]
+    fn display_decimals_in_scientific_notation_when_they_are_large_or_small() {
+        let decimal = |text| Value::Decimal(BigDecimal::from_str(text).unwrap());
+        assert_eq!(decimal("2.500").to_string(), "2.5");
+        assert_eq!(decimal("1.5e28").to_string(), "15000000000000000000000000000");
+        assert_eq!(decimal("1.5e41").to_string(), "1.5e41");
+        assert_eq!(decimal("1.5e-11").to_string(), "1.5e-11");
+    }
+
+    #[test]
+    fn literals_are_rounded_to_the_precision() {
+        assert_eq!(Value::parse("1.234567", NumberMode::Decimal(5)), Some(Value::Decimal(BigDecimal::from_str("1.2346").unwrap())));
+        assert_eq!(Value::Decimal(BigDecimal::from_str("1.2346").unwrap()).to_mode(NumberMode::Float), Ok(Value::Number(1.2346)));
+    }
+
+    #[test]
+    fn recognize_fractions() {
+        assert!(is_fraction("1/3") && is_fraction("-22/7"));
+        assert!(!is_fraction("1/") && !is_fraction("/3") && !is_fraction("1.5/2") && !is_fraction("1/2/3"));
+        assert_eq!(Value::parse("-22/7", NumberMode::Rational), Some(Value::Rational(BigRational::new((-22).into(), 7.into()))));
+        assert_eq!(Value::parse("1/0", NumberMode::Rational), None);
+    }
+
+    #[test]
+    fn decimals_with_huge_exponents_are_approximated() {
+        assert_eq!(Value::parse("1e-100001", NumberMode::Rational), Some(Value::Number(0.0)));
+        assert_eq!(Value::parse("1e100001", NumberMode::Rational), None);
+        assert_eq!(Value::Decimal(BigDecimal::from_str("1e300").unwrap()).to_mode(NumberMode::Rational).map(|v| v.kind()), Ok(ValueKind::Number));
+    }
+
+    #[test]
+    fn display_fractions_as_decimals() {
+        let third = Value::Rational(BigRational::new(1.into(), 3.into()));
+        assert_eq!(third.format(FractionDisplay::Fraction), "1/3");
+        assert_eq!(third.format(FractionDisplay::Decimal(5)), "0.33333");
+        assert_eq!(Value::Rational(BigRational::from_integer(4.into())).to_string(), "4");
+        assert_eq!(third.to_mode(NumberMode::Float), Ok(Value::Number(1.0 / 3.0)));
+    }
+
+    #[test]
+    fn recognize_complex_numbers() {
+        assert!(is_complex("i") && is_complex("-2i") && is_complex("1 + 2i") && is_complex("1.5-2.5e3i"));
+        assert!(!is_complex("2") && !is_complex("1 + i2") && !is_complex("ii"));
+        assert_eq!(Value::parse("1.5-2.5e3i", NumberMode::Complex), Some(Value::Complex(Complex64::new(1.5, -2500.0))));
+        assert_eq!(Value::parse("-i", NumberMode::Complex), Some(Value::Complex(Complex64::new(0.0, -1.0))));
+    }
+
+    #[test]
+    fn display_complex_numbers() {
+        let complex = |re, im| Value::Complex(Complex64::new(re, im));
+        assert_eq!(complex(5.0, 5.0).to_string(), "5 + 5i");
+        assert_eq!(complex(0.5, -0.5).to_string(), "0.5 - 0.5i");
+        assert_eq!(complex(0.0, -1.0).to_string(), "-i");
+        assert_eq!(complex(-2.0, 1.0).to_string(), "-2 + i");
+        assert_eq!(complex(120.0, 0.0).to_string(), "120");
+        assert_eq!(complex(0.0, 2.0).format_polar(AngleMode::Deg), "2∠90");
+        assert_eq!(complex(3.0, 0.0).format_polar(AngleMode::Deg), "3");
+    }
+
+    #[test]
+    fn complex_numbers_stay_in_complex_mode() {
+        assert_eq!(Value::Complex(Complex64::new(2.0, 0.0)).to_mode(NumberMode::Float), Ok(Value::Number(2.0)));
+        assert_eq!(Value::Complex(Complex64::new(0.0, 2.0)).to_mode(NumberMode::Float),
+            Err(CalcError::domain_error("Complex numbers are only supported in complex mode")));
+    }
+
+    #[test]
+    fn display_values_of_other_kinds() {
+        let names = Value::List(vec![Value::String("a".into()), Value::String("b".into())]);
+        assert_eq!(names.to_string(), r#"["a", "b"]"#);
+        assert_eq!(Value::Boolean(true).to_string(), "true");
+        assert_eq!(Value::Integer(-5).to_string(), "-5");
+        assert_eq!((names.kind(), Value::Integer(1).kind(), Value::Number(1.0).kind()), (ValueKind::List, ValueKind::Integer, ValueKind::Number));
+    }
+
+    #[test]
+    fn values_of_other_kinds_keep_their_kind_in_every_mode() {
+        let items = Value::List(vec![Value::Number(0.5), Value::Boolean(false)]);
+        assert_eq!(items.to_mode(NumberMode::Rational),
+            Ok(Value::List(vec![Value::Rational(BigRational::new(1.into(), 2.into())), Value::Boolean(false)])));
+        assert_eq!(Value::Integer(7).to_mode(NumberMode::Decimal(10)), Ok(Value::Integer(7)));
+        assert_eq!(Value::String("a".into()).to_decimal(10), Err(CalcError::type_error(ValueKind::Number, ValueKind::String)));
+        assert!(Value::Boolean(true).to_f64().is_nan());
+    }
+
+    #[test]
+    fn word_size_is_validated() {
+        assert_eq!(IntegerMode::new(Radix::Hex, 0, false), None);
+        assert_eq!(IntegerMode::new(Radix::Hex, 65, false), None);
+        let mode = IntegerMode::new(Radix::Hex, 64, true).unwrap();
+        let sizes: Vec<u32> = std::iter::successors(Some(mode), |mode| Some(mode.next_word_size())).take(5).map(|mode| mode.bits()).collect();
+        assert_eq!(sizes, [64, 32, 16, 8, 64]);
+    }
+
+    #[test]
+    fn integers_are_wrapped_to_the_word() {
+        let byte = IntegerMode::new(Radix::Hex, 8, false).unwrap();
+        assert_eq!(byte.parse("FF"), Some(255));
+        assert_eq!(byte.parse("1FF"), Some(255));
+        assert_eq!(byte.format(-1), "FF");
+        assert_eq!(IntegerMode::new(Radix::Hex, 8, true).unwrap().parse("FF"), Some(-1));
+        assert_eq!(IntegerMode::default().wrap(i64::MAX as i128 + 1), i64::MIN as i128);
+    }
+
+    #[test]
+    fn integers_are_written_in_the_radix() {
+        assert_eq!(IntegerMode::new(Radix::Bin, 8, false).unwrap().format(5), "101");
+        // digits of other radixes and lowercase letters are not digits
+        assert_eq!(IntegerMode::new(Radix::Hex, 8, false).unwrap().parse("ff"), None);
+        assert_eq!(IntegerMode::new(Radix::Oct, 8, false).unwrap().parse("8"), None);
+        let hex = NumberMode::Integer(IntegerMode::new(Radix::Hex, 64, true).unwrap());
+        assert_eq!(Value::Integer(17).format_in(hex), "11");
+        assert_eq!(Value::Integer(17).to_string(), "17");
+        assert_eq!(Value::List(vec![Value::Integer(17), Value::Integer(-1)]).format_in(hex), "[11, FFFFFFFFFFFFFFFF]");
+    }
+}
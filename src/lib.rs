@@ -0,0 +1,27 @@
+//! Calculator engine used by the desktop calculator.
+//!
+//! The engine does not depend on any GUI toolkit, so it can be embedded in tools and servers.
+//! [`Evaluator`] evaluates whole expressions or tokens put one by one,
+//! [`Calculator`] works on top of it and emulates a keypad calculator.
+//! The desktop calculator is built only when the `gui` feature is enabled.
+//!
+//! ```
+//! use rust_caculator::{Evaluator, NumberMode, Value};
+//!
+//! let mut evaluator = Evaluator::new();
+//! assert_eq!(evaluator.evaluate_str("2*(3+4)"), Ok(Value::Number(14.0)));
+//!
+//! evaluator.set_number_mode(NumberMode::Decimal(32));
+//! assert_eq!(evaluator.evaluate_str("0.1 + 0.2").unwrap().to_string(), "0.3");
+//! ```
+
+#![warn(missing_docs)]
+
+mod calc;
+
+pub use calc::{
+    check_arg_count, is_complex, is_decimal, is_fraction, is_postfix, tokenize, tokenize_in, AngleMode, Associativity,
+    CalcError, Calculator, Context, Evaluator, Expr, Feature, FractionDisplay, FunctionId, FunctionLib, Functor, IntegerMode,
+    Notation, NumberMode, Radix, RegisteredFunction, Span, Stack, Statement, Token, TokenKind, UserFunction, Value, ValueKind,
+    PRIORITY_ADDITIVE, PRIORITY_MULTIPLICATIVE, PRIORITY_POWER, PRIORITY_UNARY_OP, PRIORITY_USER_FUNCTION,
+};
@@ -1,22 +1,31 @@
-mod calc;
-
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use calc::Calculator;
-use calc::Feature;
+use rust_caculator::CalcError;
+use rust_caculator::Calculator;
+use rust_caculator::Feature;
+use rust_caculator::IntegerMode;
+use rust_caculator::NumberMode;
+use rust_caculator::Radix;
 
 use druid::{
     theme, AppLauncher, Color, Data, Lens, LocalizedString, RenderContext, Widget, WidgetExt,
     WindowDesc,
 };
 
-use druid::widget::{CrossAxisAlignment, Flex, Label, Painter};
+use druid::widget::{CrossAxisAlignment, Either, Flex, Label, Painter};
 
 #[derive(Clone, Data, Lens)]
 struct AppData {
     history: String,
     value: String,
+    angle_mode: String,
+    /// the trigonometric keys input their inverse functions when the 2nd key is on
+    second: bool,
+    /// the programmer keypad is shown instead of the scientific one
+    programmer: bool,
+    /// radix, word size and signedness of integers in programmer mode
+    integer_mode: String,
     caculator: Rc<RefCell<Calculator>>,
 }
 
@@ -38,8 +47,8 @@ impl AppData {
                 }
             },
             Err(s) => {
-                self.value = s;
-                self.history = caculator.build_history();
+                self.value = s.to_string();
+                self.history = mark_error(caculator.build_history(), &s);
 
                 let _ = caculator.reset();
             }
@@ -49,6 +58,8 @@ impl AppData {
     fn on_feature_key(&mut self, feature: &Feature) {
         let mut caculator = self.caculator.borrow_mut();
         let state = caculator.perform_feature(feature);
+        self.angle_mode = caculator.angle_mode().to_string();
+        self.integer_mode = caculator.integer_mode().map_or_else(String::new, |mode| mode.to_string());
         match state {
             Ok(t) => {
                 match t {
@@ -63,12 +74,47 @@ impl AppData {
             },
             Err(s) => {
                 self.value = s.to_string();
-                self.history = caculator.build_history();
+                self.history = mark_error(caculator.build_history(), &s);
 
                 let _ = caculator.reset();
             }
         };
     }
+
+    /// switch between decimals of the scientific keypad and integers of the programmer keypad
+    fn toggle_programmer(&mut self) {
+        let mut caculator = self.caculator.borrow_mut();
+        self.programmer = !self.programmer;
+        let mode = if self.programmer { NumberMode::Integer(IntegerMode::default()) } else { NumberMode::Decimal(PRECISION) };
+        caculator.set_number_mode(mode);
+        self.integer_mode = caculator.integer_mode().map_or_else(String::new, |mode| mode.to_string());
+
+        // start over since the keys of the other keypad cannot edit the expression
+        if let Ok(Some(res)) = caculator.reset() {
+            self.value = res;
+        }
+        self.history = caculator.build_history();
+    }
+}
+
+/// surround the part of the history which causes the error so that user can see it
+fn mark_error(history: String, error: &CalcError) -> String {
+    match error.span() {
+        Some(span) if span.end > span.start && span.end <= history.chars().count() => {
+            let mut marked = String::new();
+            for (i, c) in history.chars().enumerate() {
+                if i == span.start {
+                    marked.push('⟦');
+                }
+                marked.push(c);
+                if i + 1 == span.end {
+                    marked.push('⟧');
+                }
+            }
+            marked
+        }
+        _ => history,
+    }
 }
 
 fn op_button_label_id(label: String, id: String) -> impl Widget<AppData> {
@@ -120,6 +166,15 @@ fn op_feature(feature: Feature) -> impl Widget<AppData> {
         Feature::MR => "MR",
         Feature::Eval => "=",
         Feature::DEL => "⌫",
+        Feature::DRG => "DRG",
+        Feature::Negate => "±",
+        Feature::EXP => "EXP",
+        Feature::Radix(Radix::Hex) => "HEX",
+        Feature::Radix(Radix::Dec) => "DEC",
+        Feature::Radix(Radix::Oct) => "OCT",
+        Feature::Radix(Radix::Bin) => "BIN",
+        Feature::WordSize => "WORD",
+        Feature::Signed => "SIGN",
     };
 
     let label_str = label.to_string();
@@ -132,6 +187,63 @@ fn op_feature(feature: Feature) -> impl Widget<AppData> {
         .on_click(move |_ctx, data: &mut AppData, _env| data.on_feature_key(&feature))
 }
 
+/// a trigonometric key, it inputs the inverse function when the 2nd key is on
+fn trig_button(name: &'static str, inverse: &'static str) -> impl Widget<AppData> {
+    let painter = Painter::new(|ctx, _, env| {
+        let bounds = ctx.size().to_rect();
+
+        ctx.fill(bounds, &env.get(theme::PRIMARY_DARK));
+
+        if ctx.is_hot() {
+            ctx.stroke(bounds.inset(-0.5), &Color::WHITE, 1.0);
+        }
+
+        if ctx.is_active() {
+            ctx.fill(bounds, &env.get(theme::PRIMARY_LIGHT));
+        }
+    });
+
+    Label::new(move |data: &AppData, _env: &_| {
+        if data.second {
+            format!("{}⁻¹", name)
+        } else {
+            name.to_string()
+        }
+    })
+        .with_text_size(24.)
+        .center()
+        .background(painter)
+        .expand()
+        .on_click(move |_ctx, data: &mut AppData, _env| {
+            let key = if data.second { inverse } else { name };
+            data.on_exp_key(key.to_string())
+        })
+}
+
+/// the 2nd key, it stays highlighted while the trigonometric keys input their inverse functions
+fn second_button() -> impl Widget<AppData> {
+    let painter = Painter::new(|ctx, data: &AppData, env| {
+        let bounds = ctx.size().to_rect();
+
+        if data.second {
+            ctx.fill(bounds, &env.get(theme::PRIMARY_LIGHT));
+        } else {
+            ctx.fill(bounds, &env.get(theme::PRIMARY_DARK));
+        }
+
+        if ctx.is_hot() {
+            ctx.stroke(bounds.inset(-0.5), &Color::WHITE, 1.0);
+        }
+    });
+
+    Label::new("2nd")
+        .with_text_size(24.)
+        .center()
+        .background(painter)
+        .expand()
+        .on_click(|_ctx, data: &mut AppData, _env| data.second = !data.second)
+}
+
 fn digit_button(digit: char) -> impl Widget<AppData> {
     let painter = Painter::new(|ctx, _, env| {
         let bounds = ctx.size().to_rect();
@@ -183,13 +295,49 @@ fn build_calc() -> impl Widget<AppData> {
         .with_text_size(14.0)
         .lens(AppData::value)
         .padding(5.0);
+    // the mode indicator shows the integer mode in programmer mode, otherwise it switches the angle mode when it is clicked
+    let lb_angle_mode = Label::new(|data: &AppData, _env: &_| {
+        if data.programmer {
+            data.integer_mode.clone()
+        } else {
+            data.angle_mode.clone()
+        }
+    })
+        .with_text_size(10.0)
+        .padding(5.0)
+        .on_click(|_ctx, data: &mut AppData, _env| {
+            if !data.programmer {
+                data.on_feature_key(&Feature::DRG)
+            }
+        });
+    // switch to the other keypad
+    let lb_keypad = Label::new(|data: &AppData, _env: &_| {
+        if data.programmer { "SCI".to_string() } else { "PROG".to_string() }
+    })
+        .with_text_size(10.0)
+        .padding(5.0)
+        .on_click(|_ctx, data: &mut AppData, _env| data.toggle_programmer());
     Flex::column()
         .with_flex_spacer(0.2)
-        .with_child(lb_history)
+        .with_child(
+            Flex::row()
+                .with_child(lb_angle_mode)
+                .with_child(lb_keypad)
+                .with_flex_spacer(1.0)
+                .with_child(lb_history),
+        )
         .with_flex_spacer(0.2)
         .with_child(lb_result)
         .with_flex_spacer(0.2)
         .cross_axis_alignment(CrossAxisAlignment::End)
+        .with_flex_child(
+            Either::new(|data: &AppData, _env| data.programmer, programmer_keypad(), scientific_keypad()),
+            10.0,
+        )
+}
+
+fn scientific_keypad() -> impl Widget<AppData> {
+    Flex::column()
         .with_flex_child(
             flex_row(
                 op_feature(Feature::CE),
@@ -201,6 +349,50 @@ fn build_calc() -> impl Widget<AppData> {
             1.0,
         )
         .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                second_button(),
+                op_feature(Feature::DRG),
+                trig_button("sinh", "asinh"),
+                trig_button("cosh", "acosh"),
+                trig_button("tanh", "atanh"),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                op_button_label_id("log".to_string(), "log10".to_string()),
+                op_button_label_id("log₂".to_string(), "log2".to_string()),
+                op_button_label_id("eˣ".to_string(), "exp".to_string()),
+                op_button_label_id("10ˣ".to_string(), "exp10".to_string()),
+                op_button_label("e".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                op_button_label_id("xʸ".to_string(), "^".to_string()),
+                op_button_label("%".to_string()),
+                op_feature(Feature::EXP),
+                op_feature(Feature::Negate),
+                op_button_label("ln".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                op_button_label("mod".to_string()),
+                op_button_label_id("|x|".to_string(), "abs".to_string()),
+                op_button_label_id("n!".to_string(), "!".to_string()),
+                op_button_label("nPr".to_string()),
+                op_button_label("nCr".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
         .with_flex_child(
             flex_row(
                 op_button_label("(".to_string()),
@@ -217,7 +409,7 @@ fn build_calc() -> impl Widget<AppData> {
                 digit_button('7'),
                 digit_button('8'),
                 digit_button('9'),
-                op_button_label("tan".to_string()),
+                trig_button("tan", "atan"),
                 op_button_label_id("×".to_string(), "*".to_string()),
             ),
             1.0,
@@ -228,7 +420,7 @@ fn build_calc() -> impl Widget<AppData> {
                 digit_button('4'),
                 digit_button('5'),
                 digit_button('6'),
-                op_button_label("cos".to_string()),
+                trig_button("cos", "acos"),
                 op_button_label_id("−".to_string(), "-".to_string()),
             ),
             1.0,
@@ -239,7 +431,7 @@ fn build_calc() -> impl Widget<AppData> {
                 digit_button('1'),
                 digit_button('2'),
                 digit_button('3'),
-                op_button_label("sin".to_string()),
+                trig_button("sin", "asin"),
                 op_button_label("+".to_string()),
             ),
             1.0,
@@ -257,21 +449,123 @@ fn build_calc() -> impl Widget<AppData> {
         )
 }
 
+/// keys of programmer mode, the digits which are not digits of the radix are ignored
+fn programmer_keypad() -> impl Widget<AppData> {
+    Flex::column()
+        .with_flex_child(
+            flex_row(
+                op_feature(Feature::CE),
+                op_feature(Feature::C),
+                op_feature(Feature::MS),
+                op_feature(Feature::MR),
+                op_feature(Feature::DEL),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                op_feature(Feature::Radix(Radix::Hex)),
+                op_feature(Feature::Radix(Radix::Dec)),
+                op_feature(Feature::Radix(Radix::Oct)),
+                op_feature(Feature::Radix(Radix::Bin)),
+                op_feature(Feature::WordSize),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                digit_button('A'),
+                digit_button('B'),
+                digit_button('C'),
+                op_button_label("(".to_string()),
+                op_button_label(")".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                digit_button('D'),
+                digit_button('E'),
+                digit_button('F'),
+                op_button_label_id("xʸ".to_string(), "^".to_string()),
+                op_button_label_id("÷".to_string(), "/".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                digit_button('7'),
+                digit_button('8'),
+                digit_button('9'),
+                op_button_label("mod".to_string()),
+                op_button_label_id("×".to_string(), "*".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                digit_button('4'),
+                digit_button('5'),
+                digit_button('6'),
+                op_button_label_id("x²".to_string(), "²".to_string()),
+                op_button_label_id("−".to_string(), "-".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                digit_button('1'),
+                digit_button('2'),
+                digit_button('3'),
+                op_feature(Feature::Negate),
+                op_button_label("+".to_string()),
+            ),
+            1.0,
+        )
+        .with_spacer(1.0)
+        .with_flex_child(
+            flex_row(
+                op_feature(Feature::Signed),
+                digit_button('0'),
+                op_button_label("√".to_string()),
+                op_button_label_id("n!".to_string(), "!".to_string()),
+                op_feature(Feature::Eval),
+            ),
+            1.0,
+        )
+}
+
+/// number of significant digits computed and displayed by the calculator
+const PRECISION: u64 = 32;
+
 pub fn main() {
     let window = WindowDesc::new(build_calc())
-        .window_size((403., 400.))
+        .window_size((403., 640.))
         .resizable(false)
         .title(
             LocalizedString::new("calc-demo-window-title").with_placeholder("Simple Calculator"),
         );
 
+    let mut caculator = Calculator::new();
+    caculator.set_number_mode(NumberMode::Decimal(PRECISION));
     let app_data: AppData = AppData {
         history: String::new(),
         value: "0".to_string(),
-        caculator: Rc::new(RefCell::new(Calculator::new()))
+        angle_mode: caculator.angle_mode().to_string(),
+        second: false,
+        programmer: false,
+        integer_mode: String::new(),
+        caculator: Rc::new(RefCell::new(caculator))
     };
 
-    app_data.caculator.borrow_mut().add_constant("π".to_string(), "3.14159265358979323846".to_string());
+    app_data.caculator.borrow_mut().add_constant("π".to_string(), "3.1415926535897932384626433832795".to_string());
+    app_data.caculator.borrow_mut().add_constant("e".to_string(), "2.7182818284590452353602874713527".to_string());
 
     AppLauncher::with_window(window)
         .log_to_console()
@@ -0,0 +1,105 @@
+//! Exit codes and output of the terminal calculator.
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn calc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_calc"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn calc_stdin(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_calc"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// a file in the temporary directory which is removed when it is dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, content: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("calc-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn evaluate_expressions_of_arguments() {
+    let output = calc(&["-e", "x = 1 + 2", "-e", "x * 2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n6\n");
+
+    let output = calc(&["-e", "1 +", "-e", "2"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output), "error: Unexpected end of expression\n");
+}
+
+#[test]
+fn reject_invalid_arguments() {
+    for args in [&["-e"][..], &["--batch"], &["--frobnicate"], &["-e", "1", "--batch", "file"]] {
+        let output = calc(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains("Usage:"), "{:?}", args);
+    }
+}
+
+#[test]
+fn evaluate_a_batch_file() {
+    let file = TempFile::new("valid", "# a comment\nx = 4\n\n  x / 2\n");
+    let output = calc(&["--batch", file.path()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "4\n2\n");
+
+    let file = TempFile::new("invalid", "1 + 1\n  2 * y\n3\n");
+    let output = calc(&["--batch", file.path()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "2\n3\n");
+    assert_eq!(stderr(&output), "line 2, column 7: error: Variable 'y' is not defined\n");
+
+    let output = calc(&["--batch", "/nonexistent/calc/batch"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("calc: cannot open /nonexistent/calc/batch"));
+}
+
+#[test]
+fn evaluate_the_standard_input() {
+    let output = calc_stdin("2 ^ 10\n:quit\n1 / 0\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1024\n");
+
+    let output = calc_stdin("max(1, 2\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "line 1, column 4: error: Unbalanced bracket '('\n");
+}